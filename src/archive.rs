@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::md::{FileEntry, Scan, ScanRules, SkipReason};
+
+/// Largest archive entry that is loaded; headers are untrusted, so the limit
+/// applies to the bytes actually read.
+const MAX_ENTRY_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Archive formats that can be packed without extracting them first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Tar,
+    TarGz,
+    Zip,
+}
+
+impl ArchiveKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// Applies the scan rules to archive entries as they are streamed.
+struct ArchiveScanner<'a> {
+    rules: &'a ScanRules,
+    scan: Scan,
+    pruned: HashSet<String>,
+}
+
+impl ArchiveScanner<'_> {
+    fn visit(&mut self, name: &str, size: u64, reader: &mut dyn Read) -> Result<()> {
+        let name = name.trim_start_matches("./").trim_start_matches('/');
+        if name.is_empty() || name.ends_with('/') {
+            return Ok(());
+        }
+
+        // Archives list files rather than directories, so directory rules are
+        // checked on every parent prefix and each pruned directory reported once.
+        let components: Vec<&str> = name.split('/').collect();
+        for depth in 1..components.len() {
+            let prefix = components[..depth].join("/");
+            if self.pruned.contains(&prefix) {
+                return Ok(());
+            }
+            if let Some(reason) = self.rules.dir_reason(Path::new(&prefix)) {
+                self.scan.skip(prefix.clone(), true, 0, reason);
+                self.pruned.insert(prefix);
+                return Ok(());
+            }
+        }
+
+        if let Some(reason) = self.rules.file_reason(Path::new(name)) {
+            self.scan.skip(name.to_string(), false, size, reason);
+            return Ok(());
+        }
+
+        let mut bytes = Vec::with_capacity(size.min(MAX_ENTRY_BYTES) as usize);
+        let content = match reader.take(MAX_ENTRY_BYTES + 1).read_to_end(&mut bytes) {
+            Ok(read) if read as u64 > MAX_ENTRY_BYTES => Err(SkipReason::TooLarge(MAX_ENTRY_BYTES)),
+            Ok(_) => self.rules.decode(bytes),
+            Err(err) => Err(SkipReason::Unreadable(err.to_string())),
+        };
+
+        match content {
+            Ok(content) => self.scan.files.push(FileEntry {
+                path: PathBuf::from(name),
+                relative_path: name.to_string(),
+                content,
+            }),
+            Err(reason) => self.scan.skip(name.to_string(), false, size, reason),
+        }
+        Ok(())
+    }
+}
+
+fn visit_tar<R: Read>(reader: R, scanner: &mut ArchiveScanner) -> Result<()> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().context("Failed to read tar archive")? {
+        let mut entry = entry.context("Failed to read tar entry")?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().to_string();
+        let size = entry.size();
+        scanner.visit(&name, size, &mut entry)?;
+    }
+    Ok(())
+}
+
+fn visit_zip(file: File, scanner: &mut ArchiveScanner) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(file).context("Failed to read zip archive")?;
+    for index in 0..archive.len() {
+        let mut entry = archive
+            .by_index(index)
+            .context("Failed to read zip entry")?;
+        if entry.is_dir() {
+            continue;
+        }
+        let name = entry.name().to_string();
+        let size = entry.size();
+        scanner.visit(&name, size, &mut entry)?;
+    }
+    Ok(())
+}
+
+/// Scans the files inside an archive as if it were the input directory.
+pub(crate) fn scan_archive(path: &Path, kind: ArchiveKind, rules: &ScanRules) -> Result<Scan> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to open archive: {}", path.display()))?;
+    let mut scanner = ArchiveScanner {
+        rules,
+        scan: Scan::default(),
+        pruned: HashSet::new(),
+    };
+
+    match kind {
+        ArchiveKind::Tar => visit_tar(file, &mut scanner)?,
+        ArchiveKind::TarGz => visit_tar(GzDecoder::new(file), &mut scanner)?,
+        ArchiveKind::Zip => visit_zip(file, &mut scanner)?,
+    }
+
+    // Match the ordering of directory scans regardless of archive order
+    let mut scan = scanner.scan;
+    scan.files
+        .sort_by(|a, b| Path::new(&a.relative_path).cmp(Path::new(&b.relative_path)));
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pack::PackBuilder;
+    use std::io::Write;
+
+    fn tar_gz(path: &Path, entries: &[(&str, &[u8])]) {
+        let encoder = flate2::write::GzEncoder::new(
+            File::create(path).unwrap(),
+            flate2::Compression::default(),
+        );
+        let mut builder = tar::Builder::new(encoder);
+        for (name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn test_scan_tar_gz_applies_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bundle.tar.gz");
+        tar_gz(
+            &archive,
+            &[
+                ("repro/src/main.rs", b"fn main() {}"),
+                ("repro/node_modules/a/index.js", b"x"),
+                ("repro/node_modules/b/index.js", b"y"),
+                ("repro/logo.png", b"\x89PNG\0\0"),
+                ("repro/README.md", b"# Repro"),
+            ],
+        );
+
+        let scan = PackBuilder::new().root(&archive).build().unwrap();
+
+        let files: Vec<&str> = scan
+            .files
+            .iter()
+            .map(|f| f.relative_path.as_str())
+            .collect();
+        assert_eq!(files, vec!["repro/README.md", "repro/src/main.rs"]);
+        assert_eq!(scan.files[1].content, "fn main() {}");
+
+        let skipped: Vec<(&str, &str)> = scan
+            .skipped
+            .iter()
+            .map(|s| (s.relative_path.as_str(), s.reason.rule()))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![
+                ("repro/node_modules", "excluded-name"),
+                ("repro/logo.png", "binary")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_skips_oversized_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bomb.tar.gz");
+        let big = vec![b'a'; MAX_ENTRY_BYTES as usize + 1];
+        tar_gz(&archive, &[("big.txt", &big), ("small.txt", b"ok")]);
+
+        let scan = PackBuilder::new().root(&archive).build().unwrap();
+
+        assert_eq!(scan.files.len(), 1);
+        assert_eq!(scan.skipped[0].relative_path, "big.txt");
+        assert_eq!(scan.skipped[0].reason.rule(), "too-large");
+    }
+
+    #[test]
+    fn test_scan_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("bundle.zip");
+        let mut writer = zip::ZipWriter::new(File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        writer.add_directory("src/", options).unwrap();
+        writer.start_file("src/lib.rs", options).unwrap();
+        writer.write_all(b"pub fn lib() {}").unwrap();
+        writer.start_file(".env", options).unwrap();
+        writer.write_all(b"SECRET=1").unwrap();
+        writer.finish().unwrap();
+
+        let scan = PackBuilder::new().root(&archive).build().unwrap();
+
+        assert_eq!(scan.files.len(), 1);
+        assert_eq!(scan.files[0].relative_path, "src/lib.rs");
+        assert_eq!(scan.skipped[0].reason.rule(), "excluded-name");
+    }
+}
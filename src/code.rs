@@ -1,47 +1,60 @@
-use serde_json::Value;
-use std::collections::HashMap;
+use std::env;
 use std::fs;
 use std::io::{self, BufRead, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
 
+use crate::compact;
+use crate::config::Config;
+use crate::filter::PathFilter;
+use crate::md::estimate_tokens;
 use crate::models::claude::default_claude;
 use crate::models::deepseek::default_deepseek;
 use crate::models::google::default_google;
 use crate::models::openai::default_openai;
-use crate::models::{
-    AppError, ContentBlock, Message, Model, ModelResponse, ModelType, Tool, ToolSchema,
-    ToolSchemaProperty,
-};
+use crate::models::{AppError, ContentBlock, Message, Model, ModelResponse, ModelType};
+use crate::session::{Session, SessionStore};
+use crate::tools::checkpoint::Checkpoints;
+use crate::tools::permissions::{self, Answer};
+use crate::tools::run_command;
+use crate::tools::{Access, Permissions, Policy, ToolContext, ToolRegistry, Workspace};
 
 type Result<T> = std::result::Result<T, AppError>;
 
-type ToolFunction = fn(Value) -> Result<String>;
-
-struct ToolDefinition {
-    name: String,
-    description: String,
-    schema: ToolSchema,
-    function: ToolFunction,
+/// Options for [`Agent::new`].
+#[derive(Debug, Default)]
+pub struct AgentOptions {
+    /// Workspace root for the file tools; overrides `[code.sandbox] root` and the cwd.
+    pub root: Option<PathBuf>,
+    /// Run every tool call not denied by `[code.permissions]` without asking.
+    pub yes: bool,
+    /// Which saved conversation, if any, to pick up.
+    pub session: SessionStart,
 }
 
-impl ToolDefinition {
-    fn to_api_tool(&self) -> Tool {
-        Tool {
-            name: self.name.clone(),
-            description: self.description.clone(),
-            input_schema: self.schema.clone(),
-        }
-    }
+/// How [`Agent::run`] picks its session.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum SessionStart {
+    #[default]
+    New,
+    /// The project's most recently updated session.
+    Continue,
+    /// A session by id or unique prefix; `None` lets the user pick one.
+    Resume(Option<String>),
 }
 
 pub struct Agent {
     model: Box<dyn Model>,
-    tools: Vec<ToolDefinition>,
+    tools: ToolRegistry,
+    context: ToolContext,
+    permissions: Permissions,
+    session: SessionStart,
     system_prompt: String,
 }
 
 impl Agent {
-    pub fn new(model_type: ModelType) -> Result<Self> {
+    pub fn new(model_type: ModelType, options: AgentOptions) -> Result<Self> {
         let model: Box<dyn Model> = match model_type {
             ModelType::Claude => Box::new(default_claude()?),
             ModelType::Google => Box::new(default_google()?),
@@ -55,27 +68,53 @@ impl Agent {
         let system_prompt = fs::read_to_string("src/system_prompt.txt")
             .map_err(|e| AppError(format!("Failed to load system prompt: {}", e)))?;
 
+        let config = Config::load(Path::new("."))?;
+        let mut tools = ToolRegistry::with_defaults();
+        tools.apply_config(&config.code);
+
+        let cwd = env::current_dir().map_err(|e| AppError(e.to_string()))?;
+        let mut sandbox = config.code.sandbox.clone();
+        if let Some(root) = options.root {
+            sandbox.root = Some(root);
+        }
+        let workspace = Workspace::from_config(&cwd, &sandbox)?;
+        println!("Workspace root: {}", workspace.root().display());
+
         Ok(Agent {
             model,
-            tools: vec![
-                read_file_definition(),
-                list_files_definition(),
-                edit_file_definition(),
-            ],
+            tools,
+            context: ToolContext::new(workspace)
+                .with_filter(PathFilter::from_config(&config.filter)),
+            permissions: Permissions::new(config.code.permissions, options.yes)?,
+            session: options.session,
             system_prompt,
         })
     }
 
+    /// Tools offered to the model; register custom tools here before calling `run`.
+    pub fn tools_mut(&mut self) -> &mut ToolRegistry {
+        &mut self.tools
+    }
+
     pub async fn run(&self) -> Result<()> {
-        let mut conversation: Vec<Message> = Vec::new();
         let stdin = io::stdin();
         let mut reader = stdin.lock();
         let mut buffer = String::new();
+        let store = SessionStore::for_project(self.context.root())?;
+        let Some((mut session, mut conversation)) = self.open_session(&store, &mut reader)? else {
+            return Ok(());
+        };
+        let mut permissions = self.permissions.clone();
+        let mut checkpoints = Checkpoints::new();
+        run_command::exit_on_ctrl_c_when_idle();
+        // Told to the model with the next message, e.g. after `/restore`.
+        let mut notes: Vec<String> = Vec::new();
 
         println!("Chat with {} (use 'ctrl-c' to quit)", self.model.name());
 
         let mut read_user_input = true;
         loop {
+            save_session(&mut session, &conversation);
             if read_user_input {
                 print!("\x1b[94mYou\x1b[0m: ");
                 io::stdout().flush().map_err(|e| AppError(e.to_string()))?;
@@ -89,10 +128,30 @@ impl Agent {
                     break;
                 }
 
-                let user_input = buffer.trim().to_string();
+                let mut user_input = buffer.trim().to_string();
                 if user_input.is_empty() {
                     continue;
                 }
+                if let Some(command) = user_input.strip_prefix('/') {
+                    if command.trim() == "compact" {
+                        match self.compact(&mut conversation, &mut session).await {
+                            Ok(true) => {}
+                            Ok(false) => println!("Nothing to compact yet."),
+                            Err(e) => eprintln!("\x1b[91mError: Failed to compact: {}\x1b[0m", e),
+                        }
+                        continue;
+                    }
+                    if let Some(note) = self.run_command(command, &mut checkpoints)? {
+                        notes.push(note);
+                    }
+                    continue;
+                }
+
+                checkpoints.start_turn(&user_input);
+                if !notes.is_empty() {
+                    user_input = format!("{}\n\n{}", notes.join("\n"), user_input);
+                    notes.clear();
+                }
 
                 conversation.push(Message {
                     role: "user".to_string(),
@@ -100,6 +159,14 @@ impl Agent {
                 });
             }
 
+            let limit = self.model.context_window() * compact::COMPACT_AT_PERCENT / 100;
+            if self.request_tokens(&conversation) > limit {
+                println!("Conversation is nearing the context limit; compacting older turns...");
+                if let Err(e) = self.compact(&mut conversation, &mut session).await {
+                    eprintln!("\x1b[91mWarning:\x1b[0m Failed to compact: {}", e);
+                }
+            }
+
             let response = self.run_inference(&conversation).await?;
 
             let assistant_content = response.content.clone();
@@ -128,9 +195,48 @@ impl Agent {
                             continue;
                         }
 
-                        println!("\x1b[92mtool\x1b[0m: {}({})", name, input);
+                        // Changes are shown as a diff or command, whatever the policy.
+                        match self.tools.get(&name) {
+                            Some(tool) if tool.is_mutating() => println!(
+                                "\x1b[92mtool\x1b[0m: {}\n{}",
+                                name,
+                                tool.preview(&self.context, &input)
+                            ),
+                            _ => println!("\x1b[92mtool\x1b[0m: {}({})", name, input),
+                        }
 
-                        let tool_result = self.execute_tool(&id, &name, &input);
+                        if let Some(denial) =
+                            self.approve(&mut permissions, &mut reader, &name, &input)?
+                        {
+                            println!("\x1b[91mdenied\x1b[0m: {}", denial);
+                            tool_results.push(ContentBlock::ToolResult {
+                                tool_use_id: id.clone(),
+                                content: denial,
+                                error: Some(true),
+                            });
+                            continue;
+                        }
+
+                        if let Some(tool) = self.tools.get(&name)
+                            && tool.is_mutating()
+                        {
+                            if let Err(e) = checkpoints.record(&self.context, &tool.paths(&input)) {
+                                let message =
+                                    format!("Not run: failed to record a checkpoint: {}", e);
+                                eprintln!("\x1b[91mError: {}\x1b[0m", message);
+                                tool_results.push(ContentBlock::ToolResult {
+                                    tool_use_id: id.clone(),
+                                    content: message,
+                                    error: Some(true),
+                                });
+                                continue;
+                            }
+                            if let Some(command) = tool.command(&input) {
+                                checkpoints.record_command(&command);
+                            }
+                        }
+
+                        let tool_result = self.tools.call(&self.context, &name, input).await;
 
                         match tool_result {
                             Ok(result_content) => {
@@ -212,327 +318,291 @@ impl Agent {
             }
         }
 
+        save_session(&mut session, &conversation);
+        if !conversation.is_empty() {
+            println!(
+                "Session {} saved; resume it with `pai code --resume {}`.",
+                session.id, session.id
+            );
+        }
         Ok(())
     }
 
-    async fn run_inference(&self, conversation: &[Message]) -> Result<ModelResponse> {
-        let api_tools = if self.model.supports_tools() {
-            Some(
-                self.tools
-                    .iter()
-                    .map(|t| t.to_api_tool())
-                    .collect::<Vec<_>>(),
-            )
-        } else {
-            None
+    /// Starts or reopens the session chosen by [`AgentOptions::session`]. Returns
+    /// `None` if the user backed out of the session picker.
+    fn open_session<R: BufRead>(
+        &self,
+        store: &SessionStore,
+        reader: &mut R,
+    ) -> Result<Option<(Session, Vec<Message>)>> {
+        let id = match &self.session {
+            SessionStart::New => {
+                let session = store.create(self.context.root(), self.model.name());
+                return Ok(Some((session, Vec::new())));
+            }
+            SessionStart::Continue => match store.latest()? {
+                Some(info) => info.id,
+                None => {
+                    println!("No earlier session for this project; starting a new one.");
+                    let session = store.create(self.context.root(), self.model.name());
+                    return Ok(Some((session, Vec::new())));
+                }
+            },
+            SessionStart::Resume(Some(id)) => id.clone(),
+            SessionStart::Resume(None) => {
+                let sessions = store.list()?;
+                if sessions.is_empty() {
+                    println!("No saved sessions for this project.");
+                    return Ok(None);
+                }
+                for (index, info) in sessions.iter().enumerate().take(20) {
+                    println!(
+                        "  {:>2}. {}  {}  {}",
+                        index + 1,
+                        info.updated.format("%Y-%m-%d %H:%M"),
+                        info.id,
+                        info.title.chars().take(60).collect::<String>()
+                    );
+                }
+                print!("Resume which session? [1-{}] ", sessions.len().min(20));
+                io::stdout().flush().map_err(|e| AppError(e.to_string()))?;
+                let mut answer = String::new();
+                reader
+                    .read_line(&mut answer)
+                    .map_err(|e| AppError(e.to_string()))?;
+                match answer.trim().parse::<usize>() {
+                    Ok(n) if (1..=sessions.len().min(20)).contains(&n) => {
+                        sessions[n - 1].id.clone()
+                    }
+                    _ => {
+                        println!("No session chosen.");
+                        return Ok(None);
+                    }
+                }
+            }
         };
 
-        self.model
-            .run_inference(
-                conversation,
-                api_tools.as_deref(),
-                Some(&self.system_prompt),
-            )
-            .await
-    }
-
-    fn execute_tool(&self, _id: &str, name: &str, input: &Value) -> Result<String> {
-        let tool = self
-            .tools
+        let (session, conversation) = store.open(&id)?;
+        println!(
+            "Resumed session {} ({} messages).",
+            session.id,
+            conversation.len()
+        );
+        let last_reply = conversation
             .iter()
-            .find(|t| t.name == name)
-            .ok_or_else(|| AppError(format!("Tool '{}' not found.", name)))?;
-
-        (tool.function)(input.clone())
-    }
-}
-
-fn read_file_definition() -> ToolDefinition {
-    let mut properties = HashMap::new();
-    properties.insert(
-        "path".to_string(),
-        ToolSchemaProperty {
-            property_type: "string".to_string(),
-            description: "The relative path of the file to read.".to_string(),
-        },
-    );
-    let required = vec!["path".to_string()];
-
-    ToolDefinition {
-        name: "read_file".to_string(),
-        description: "Read the entire contents of a given relative file path. Use this when you want to see what's inside a file.".to_string(),
-        schema: ToolSchema {
-            schema_type: "object".to_string(),
-            properties,
-            required: Some(required),
-        },
-        function: read_file_function,
-    }
-}
-
-fn read_file_function(input: Value) -> Result<String> {
-    let path = input
-        .get("path")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| AppError("Missing required 'path' parameter for read_file".to_string()))?;
-
-    fs::read_to_string(path).map_err(|e| AppError(format!("Failed to read file '{}': {}", path, e)))
-}
-
-fn list_files_definition() -> ToolDefinition {
-    let mut properties = HashMap::new();
-    properties.insert(
-        "path".to_string(),
-        ToolSchemaProperty {
-            property_type: "string".to_string(),
-            description: "Optional relative directory path to list files from. Defaults to current directory ('.') if not provided.".to_string(),
-        },
-    );
-
-    ToolDefinition {
-        name: "list_files".to_string(),
-        description: "List files and directories recursively starting from a given path. If the path is a file, lists only that file. If no path is provided, lists files in the current directory.".to_string(),
-        schema: ToolSchema {
-            schema_type: "object".to_string(),
-            properties,
-            required: Some(Vec::new()),
-        },
-        function: list_files_function,
-    }
-}
-
-fn list_files_function(input: Value) -> Result<String> {
-    let start_path_str = input
-        .get("path")
-        .and_then(|v| v.as_str())
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .unwrap_or(".");
-    let start_path = Path::new(start_path_str);
-
-    let mut files = Vec::new();
-
-    visit_dirs_recursive(start_path, start_path, &mut files)?;
-
-    serde_json::to_string(&files)
-        .map_err(|e| AppError(format!("Failed to serialize file list: {}", e)))
-}
-
-// Helper function adapted from md.rs to check if a path should be skipped by tools
-fn should_skip_tool_path(path: &Path) -> bool {
-    const SKIP_DIRS: &[&str] = &[
-        "target",
-        "build",
-        "dist",
-        "node_modules",
-        ".venv",
-        "venv",
-        "__pycache__",
-        ".pytest_cache",
-        ".idea",
-        ".vscode",
-        ".next",
-        ".nuxt",
-        ".docusaurus",
-        ".cargo",
-        ".rustup",
-        // Add other common build/dependency directories if needed
-    ];
-
-    // Define specific dotfiles/dotdirs to skip
-    const SKIP_DOTFILES: &[&str] = &[
-        ".git",
-        ".env",
-        ".DS_Store", // Example common dotfile
-                     // Add other specific dotfiles/dotdirs to skip
-    ];
-
-    path.components().any(|component| {
-        if let Some(name) = component.as_os_str().to_str() {
-            // Skip if it's a specific dotfile/dotdir OR in the general skip list
-            SKIP_DOTFILES.contains(&name) || SKIP_DIRS.contains(&name)
-        } else {
-            false // Ignore non-UTF8 components if any
+            .rev()
+            .filter(|m| m.role == "assistant")
+            .find_map(|m| {
+                m.content.iter().find_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text),
+                    _ => None,
+                })
+            });
+        if let Some(text) = last_reply {
+            println!("\x1b[93m{}\x1b[0m: {}", self.model.name(), text);
         }
-    })
-}
-
-fn visit_dirs_recursive(
-    current_path: &Path,
-    base_path: &Path,
-    files: &mut Vec<String>,
-) -> Result<()> {
-    if !current_path.exists() {
-        return Err(AppError(format!(
-            "Path does not exist: {}",
-            current_path.display()
-        )));
+        Ok(Some((session, conversation)))
     }
 
-    let display_path = current_path
-        .strip_prefix(base_path.parent().unwrap_or(base_path))
-        .unwrap_or(current_path);
-
-    if current_path.is_dir() {
-        if current_path != base_path && !should_skip_tool_path(current_path) {
-            files.push(format!("{}/", display_path.to_string_lossy()));
-        }
+    /// Handles a `/command` typed at the prompt. Returns a note for the model
+    /// when the command changed files behind its back.
+    fn run_command(&self, command: &str, checkpoints: &mut Checkpoints) -> Result<Option<String>> {
+        let mut words = command.split_whitespace();
+        let name = words.next().unwrap_or("");
+        let id = match words.next().map(str::parse::<usize>) {
+            Some(Ok(id)) => Some(id),
+            Some(Err(_)) => {
+                println!("Checkpoint ids are numbers; see /checkpoints.");
+                return Ok(None);
+            }
+            None => None,
+        };
 
-        match fs::read_dir(current_path) {
-            Ok(entries) => {
-                for entry_result in entries {
-                    match entry_result {
-                        Ok(entry) => {
-                            let path = entry.path();
-                            if !should_skip_tool_path(&path) {
-                                visit_dirs_recursive(&path, base_path, files)?;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "Warning: Failed to read entry in '{}': {}. Skipping.",
-                                current_path.display(),
-                                e
-                            );
-                        }
-                    }
+        match name {
+            "checkpoints" => {
+                if checkpoints.list().is_empty() {
+                    println!(
+                        "No checkpoints yet; one is recorded before each turn's first change."
+                    );
+                }
+                for checkpoint in checkpoints.list() {
+                    let paths: Vec<String> = checkpoint
+                        .paths()
+                        .map(|p| p.display().to_string())
+                        .collect();
+                    println!(
+                        "  {:>3}  {}  {}\n       {}",
+                        checkpoint.id,
+                        checkpoint.created.format("%H:%M:%S"),
+                        checkpoint.label.chars().take(60).collect::<String>(),
+                        paths.join(", ")
+                    );
                 }
             }
-            Err(e) => {
-                return Err(AppError(format!(
-                    "Failed to read directory '{}': {}",
-                    current_path.display(),
-                    e
+            "diff" => match id.or(checkpoints.latest()) {
+                Some(id) => match checkpoints.diff(&self.context, id) {
+                    Ok(diff) => print!("{}", diff),
+                    Err(e) => eprintln!("\x1b[91mError: {}\x1b[0m", e),
+                },
+                None => println!("No checkpoints yet."),
+            },
+            "restore" => {
+                let Some(id) = id else {
+                    println!("Usage: /restore <id>");
+                    return Ok(None);
+                };
+                let commands: Vec<String> = checkpoints
+                    .commands_since(id)
+                    .into_iter()
+                    .map(String::from)
+                    .collect();
+                let restored = match checkpoints.restore(&self.context, id) {
+                    Ok(restored) => restored,
+                    Err(e) => {
+                        eprintln!("\x1b[91mError: {}\x1b[0m", e);
+                        return Ok(None);
+                    }
+                };
+                if !commands.is_empty() {
+                    println!(
+                        "\x1b[91mWarning:\x1b[0m changes made by these commands since checkpoint {} were not undone:",
+                        id
+                    );
+                    for command in &commands {
+                        println!("  $ {}", command);
+                    }
+                }
+                let paths: Vec<String> = restored.iter().map(|p| p.display().to_string()).collect();
+                println!("Restored {} file(s): {}", paths.len(), paths.join(", "));
+                return Ok(Some(format!(
+                    "[The user restored these files to their state before an earlier turn: {}. Re-read them before editing.]",
+                    paths.join(", ")
                 )));
             }
+            _ => println!("Commands: /checkpoints, /diff [id], /restore <id>, /compact"),
         }
-    } else if current_path.is_file() {
-        if !should_skip_tool_path(current_path) {
-            files.push(display_path.to_string_lossy().to_string());
-        }
-    } else {
-        eprintln!(
-            "Warning: Skipping non-directory/non-file path: {}",
-            current_path.display()
-        );
+        Ok(None)
     }
-    Ok(())
-}
 
-fn edit_file_definition() -> ToolDefinition {
-    let mut properties = HashMap::new();
-    properties.insert(
-        "path".to_string(),
-        ToolSchemaProperty {
-            property_type: "string".to_string(),
-            description: "The relative path to the file to write or create.".to_string(),
-        },
-    );
-    properties.insert(
-        "content".to_string(),
-        ToolSchemaProperty {
-            property_type: "string".to_string(),
-            description: "The full new content for the file. If the file exists, its entire content will be replaced. If it doesn't exist, it will be created with this content.".to_string(),
-        },
-    );
-    let required = vec!["path".to_string(), "content".to_string()];
-
-    ToolDefinition {
-        name: "edit_file".to_string(),
-        description: "Writes or overwrites a file with the provided content. If the file path doesn't exist, it (and any necessary parent directories) will be created. If the file exists, its content will be completely replaced.".to_string(),
-        schema: ToolSchema {
-            schema_type: "object".to_string(),
-            properties,
-            required: Some(required),
-        },
-        function: write_or_create_file_function,
-    }
-}
+    /// Applies the permission policy to a tool call, prompting when it says `ask`.
+    ///
+    /// Returns the message to send back to the model if the call must not run.
+    fn approve<R: BufRead>(
+        &self,
+        permissions: &mut Permissions,
+        reader: &mut R,
+        name: &str,
+        input: &Value,
+    ) -> Result<Option<String>> {
+        let Some(tool) = self.tools.get(name) else {
+            return Ok(None);
+        };
 
-fn write_or_create_file_function(input: Value) -> Result<String> {
-    let path_str = input
-        .get("path")
-        .and_then(|v| v.as_str())
-        .map(|s| s.trim())
-        .filter(|s| !s.is_empty())
-        .ok_or_else(|| {
-            AppError("Missing or empty required 'path' parameter for edit_file".to_string())
-        })?;
-
-    let content = input
-        .get("content")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| {
-            AppError("Missing required 'content' parameter for edit_file".to_string())
-        })?;
-
-    let path = Path::new(path_str);
-
-    if let Some(parent) = path.parent() {
-        if !parent.as_os_str().is_empty() {
-            if !parent.exists() {
-                fs::create_dir_all(parent).map_err(|e| {
-                    AppError(format!(
-                        "Failed to create directory '{}': {}",
-                        parent.display(),
-                        e
-                    ))
-                })?;
-            } else if !parent.is_dir() {
-                return Err(AppError(format!(
-                    "Cannot create directory because path '{}' exists and is not a directory.",
-                    parent.display()
-                )));
-            }
+        let paths: Vec<PathBuf> = tool
+            .paths(input)
+            .iter()
+            .map(|path| match self.context.resolve(path, Access::Read) {
+                Ok(resolved) => self.context.relative(&resolved).to_path_buf(),
+                Err(_) => PathBuf::from(path),
+            })
+            .collect();
+        let paths: Vec<&Path> = paths.iter().map(|p| p.as_path()).collect();
+
+        let command = tool.command(input);
+        match permissions.decide(name, tool.is_mutating(), &paths, command.as_deref()) {
+            Policy::Allow => Ok(None),
+            Policy::Deny => Ok(Some(permissions::denial_message(name, "", true))),
+            Policy::Ask => match permissions::ask(reader, &mut io::stdout(), name)? {
+                Answer::AllowOnce => Ok(None),
+                Answer::AllowForSession => {
+                    permissions.allow_for_session(name);
+                    Ok(None)
+                }
+                Answer::Deny(feedback) => {
+                    Ok(Some(permissions::denial_message(name, &feedback, false)))
+                }
+            },
         }
     }
 
-    fs::write(path, content).map_err(|e| {
-        AppError(format!(
-            "Failed to write to file '{}': {}",
-            path.display(),
-            e
-        ))
-    })?;
+    /// Estimated size of the next request: system prompt, tool definitions and
+    /// conversation.
+    fn request_tokens(&self, conversation: &[Message]) -> usize {
+        let tools = if self.model.supports_tools() {
+            serde_json::to_string(&self.tools.api_tools()).map_or(0, |json| estimate_tokens(&json))
+        } else {
+            0
+        };
+        estimate_tokens(&self.system_prompt) + tools + compact::conversation_tokens(conversation)
+    }
 
-    Ok(format!("Successfully wrote content to {}", path_str))
-}
+    /// Replaces the older part of the conversation with a summary written by
+    /// the model, keeping recent messages. Returns `false` if there was
+    /// nothing old enough to summarize.
+    async fn compact(
+        &self,
+        conversation: &mut Vec<Message>,
+        session: &mut Session,
+    ) -> Result<bool> {
+        let keep = self.model.context_window() * compact::KEEP_PERCENT / 100;
+        let Some(split) = compact::split_point(conversation, keep) else {
+            return Ok(false);
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*; // Import items from the outer module
-    use std::path::Path;
-
-    #[test]
-    fn test_should_skip_tool_path_hidden() {
-        assert!(should_skip_tool_path(Path::new(".git")));
-        assert!(should_skip_tool_path(Path::new(".env")));
-        assert!(should_skip_tool_path(Path::new("src/.hidden_file")));
-        assert!(should_skip_tool_path(Path::new(".config/settings.toml")));
-    }
+        let before = compact::conversation_tokens(conversation);
+        let request = compact::summary_request(&conversation[..split]);
+        let response = self
+            .model
+            .run_inference(&[request], None, Some(compact::SUMMARY_PROMPT))
+            .await?;
+        let summary: Vec<String> = response
+            .content
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text),
+                _ => None,
+            })
+            .collect();
+        let summary = summary.join("\n");
+        if summary.trim().is_empty() {
+            return Err(AppError("the model returned an empty summary".to_string()));
+        }
 
-    #[test]
-    fn test_should_skip_tool_path_build_dirs() {
-        assert!(should_skip_tool_path(Path::new("target")));
-        assert!(should_skip_tool_path(Path::new("node_modules")));
-        assert!(should_skip_tool_path(Path::new("project/target/debug")));
-        assert!(should_skip_tool_path(Path::new("app/node_modules/package")));
-        assert!(should_skip_tool_path(Path::new("venv/lib/python")));
+        let kept = conversation.split_off(split);
+        *conversation = compact::compacted(&summary, kept);
+        if let Err(e) = session.rewrite(conversation) {
+            eprintln!("\x1b[91mWarning:\x1b[0m Failed to save session: {}", e);
+        }
+        println!(
+            "Compacted {} earlier messages into a summary (~{} -> ~{} tokens).",
+            split,
+            before,
+            compact::conversation_tokens(conversation)
+        );
+        Ok(true)
     }
 
-    #[test]
-    fn test_should_skip_tool_path_valid() {
-        assert!(!should_skip_tool_path(Path::new("src/main.rs")));
-        assert!(!should_skip_tool_path(Path::new("README.md")));
-        assert!(!should_skip_tool_path(Path::new("scripts/build.sh")));
-        assert!(!should_skip_tool_path(Path::new("docs/api.html")));
-        assert!(!should_skip_tool_path(Path::new(
-            ".github/workflows/ci.yml"
-        ))); // .github is allowed
+    async fn run_inference(&self, conversation: &[Message]) -> Result<ModelResponse> {
+        let api_tools = if self.model.supports_tools() {
+            Some(self.tools.api_tools())
+        } else {
+            None
+        };
+
+        self.model
+            .run_inference(
+                conversation,
+                api_tools.as_deref(),
+                Some(&self.system_prompt),
+            )
+            .await
     }
+}
 
-    // TODO: Add tests for list_files_function using temp directories
-    // TODO: Add tests for read_file_function using temp files
-    // TODO: Add tests for write_or_create_file_function using temp files
-    // TODO: Add tests for Agent::execute_tool
+/// Writes new messages to the session file; a failure only warns, so a full
+/// disk does not end the conversation.
+fn save_session(session: &mut Session, conversation: &[Message]) {
+    if let Err(e) = session.save(conversation) {
+        eprintln!("\x1b[91mWarning:\x1b[0m Failed to save session: {}", e);
+    }
 }
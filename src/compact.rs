@@ -0,0 +1,197 @@
+//! Shrinking a `pai code` conversation that nears the model's context window:
+//! older turns are replaced by a model-written summary, recent ones are kept.
+
+use crate::md::estimate_tokens;
+use crate::models::{ContentBlock, Message};
+
+/// Compact once the estimated request size passes this share of the window.
+pub const COMPACT_AT_PERCENT: usize = 80;
+/// Share of the window the kept recent messages may use.
+pub const KEEP_PERCENT: usize = 20;
+/// Characters of each tool result shown to the summarizer.
+const MAX_RESULT_CHARS: usize = 2_000;
+
+/// Marks the message that carries the summary.
+pub const SUMMARY_HEADER: &str = "[Summary of the earlier conversation]";
+
+pub const SUMMARY_PROMPT: &str = "You summarize a coding session between a user and an assistant with file and shell tools so the assistant can continue it without the original messages. Keep: the user's goals and instructions, decisions made, files read or changed (with paths), commands run and their outcome, errors still open, and what was about to happen next. Be specific and concise; use bullet points. Reply with the summary only.";
+
+/// Rough token count of one message: its text, tool calls and tool results.
+pub fn message_tokens(message: &Message) -> usize {
+    let content: usize = message
+        .content
+        .iter()
+        .map(|block| match block {
+            ContentBlock::Text { text } => estimate_tokens(text),
+            ContentBlock::ToolUse { name, input, .. } => {
+                estimate_tokens(name) + estimate_tokens(&input.to_string())
+            }
+            ContentBlock::ToolResult { content, .. } => estimate_tokens(content),
+        })
+        .sum();
+    // Role and per-block framing.
+    content + 4 * message.content.len() + 4
+}
+
+pub fn conversation_tokens(conversation: &[Message]) -> usize {
+    conversation.iter().map(message_tokens).sum()
+}
+
+/// Whether the kept part of a conversation may start at `index` without
+/// separating a tool call from its result.
+fn can_split_at(conversation: &[Message], index: usize) -> bool {
+    let has = |message: &Message, tool_use: bool| {
+        message.content.iter().any(|block| match block {
+            ContentBlock::ToolUse { .. } => tool_use,
+            ContentBlock::ToolResult { .. } => !tool_use,
+            ContentBlock::Text { .. } => false,
+        })
+    };
+    !has(&conversation[index], false) && !has(&conversation[index - 1], true)
+}
+
+/// Index of the first message to keep: the earliest valid split whose tail
+/// fits in `keep_tokens`, or else the latest one. `None` if nothing before
+/// the last message can be summarized.
+pub fn split_point(conversation: &[Message], keep_tokens: usize) -> Option<usize> {
+    let mut tail = 0;
+    let mut best = None;
+    for index in (1..conversation.len()).rev() {
+        tail += message_tokens(&conversation[index]);
+        if !can_split_at(conversation, index) {
+            continue;
+        }
+        if best.is_some() && tail > keep_tokens {
+            break;
+        }
+        best = Some(index);
+    }
+    best
+}
+
+/// Plain-text rendering of `messages` for the summarizer, with long tool
+/// results cut.
+fn transcript(messages: &[Message]) -> String {
+    let mut out = String::new();
+    for message in messages {
+        for block in &message.content {
+            match block {
+                ContentBlock::Text { text } => {
+                    out.push_str(&format!("{}: {}\n\n", message.role, text));
+                }
+                ContentBlock::ToolUse { name, input, .. } => {
+                    out.push_str(&format!("assistant called {}({})\n\n", name, input));
+                }
+                ContentBlock::ToolResult { content, error, .. } => {
+                    let label = if error.unwrap_or(false) {
+                        "tool error"
+                    } else {
+                        "tool result"
+                    };
+                    let cut = match content.char_indices().nth(MAX_RESULT_CHARS) {
+                        Some((end, _)) => format!("{}... [cut]", &content[..end]),
+                        None => content.clone(),
+                    };
+                    out.push_str(&format!("{}: {}\n\n", label, cut));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// The single user message asking the model to summarize `messages`.
+pub fn summary_request(messages: &[Message]) -> Message {
+    Message {
+        role: "user".to_string(),
+        content: vec![ContentBlock::Text {
+            text: format!(
+                "Summarize this conversation:\n\n<conversation>\n{}</conversation>",
+                transcript(messages)
+            ),
+        }],
+    }
+}
+
+/// The conversation that replaces the old one: the summary, then the kept
+/// messages. A kept user message absorbs the summary so roles still alternate.
+pub fn compacted(summary: &str, mut kept: Vec<Message>) -> Vec<Message> {
+    let summary = ContentBlock::Text {
+        text: format!("{}\n{}", SUMMARY_HEADER, summary.trim()),
+    };
+    match kept.first_mut() {
+        Some(first) if first.role == "user" => {
+            first.content.insert(0, summary);
+            kept
+        }
+        _ => {
+            let mut conversation = vec![Message {
+                role: "user".to_string(),
+                content: vec![summary],
+            }];
+            conversation.extend(kept);
+            conversation
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(role: &str, text: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: vec![ContentBlock::Text {
+                text: text.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_split_keeps_tool_pairs_together() {
+        let tool_use = Message {
+            role: "assistant".to_string(),
+            content: vec![ContentBlock::ToolUse {
+                id: "1".to_string(),
+                name: "read_file".to_string(),
+                input: json!({"path": "a.rs"}),
+            }],
+        };
+        let tool_result = Message {
+            role: "user".to_string(),
+            content: vec![ContentBlock::ToolResult {
+                tool_use_id: "1".to_string(),
+                content: "x".repeat(400),
+                error: None,
+            }],
+        };
+        let conversation = vec![
+            text("user", "first"),
+            text("assistant", "ok"),
+            text("user", "read a.rs"),
+            tool_use,
+            tool_result,
+            text("assistant", "done"),
+        ];
+
+        // Everything from "read a.rs" on is too big, so the latest split wins;
+        // it may not fall between the call and its result.
+        assert_eq!(split_point(&conversation, 10), Some(5));
+        assert_eq!(split_point(&conversation, 1_000), Some(1));
+        assert_eq!(split_point(&conversation[..1], 1_000), None);
+
+        let compact = compacted("- read a.rs", conversation[2..].to_vec());
+        assert_eq!(compact.len(), 4);
+        assert!(matches!(
+            &compact[0].content[0],
+            ContentBlock::Text { text } if text.starts_with(SUMMARY_HEADER)
+        ));
+        assert_eq!(compact[0].content.len(), 2);
+
+        let compact = compacted("- all done", conversation[5..].to_vec());
+        assert_eq!(compact.len(), 2);
+        assert_eq!(compact[0].role, "user");
+    }
+}
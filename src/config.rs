@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::filter::FilterConfig;
+use crate::template::PackFormat;
+use crate::tools::command::CommandToolConfig;
+use crate::tools::{PermissionConfig, RunCommandConfig, SandboxConfig};
+
+/// Name of the per-project configuration file, read from the working directory.
+pub const CONFIG_FILE: &str = ".pai.toml";
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub md: MdConfig,
+    #[serde(default)]
+    pub filter: FilterConfig,
+    #[serde(default)]
+    pub code: CodeConfig,
+}
+
+/// `[code]` section: tools available to `pai code`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct CodeConfig {
+    /// Built-in or configured tools to hide from the agent.
+    #[serde(default)]
+    pub disabled_tools: Vec<String>,
+    /// Extra tools that run a fixed shell command (`[[code.tools]]`).
+    #[serde(default)]
+    pub tools: Vec<CommandToolConfig>,
+    /// Workspace root and path rules for the file tools.
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// Which tool calls run freely, need approval, or are refused.
+    #[serde(default)]
+    pub permissions: PermissionConfig,
+    /// Timeout and output limits for `run_command` and `[[code.tools]]`.
+    #[serde(default)]
+    pub run_command: RunCommandConfig,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MdConfig {
+    /// Built-in output format used when no template is configured.
+    pub format: Option<PackFormat>,
+    pub template: Option<TemplateConfig>,
+    /// Instructions placed before the files (literal text or `@file`).
+    pub prompt: Option<String>,
+    /// Instructions placed after the files (literal text or `@file`).
+    pub suffix: Option<String>,
+    #[serde(default)]
+    pub repeat_prompt: bool,
+}
+
+/// A pack template, either a path to a template file or inline sections.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum TemplateConfig {
+    File(String),
+    Sections {
+        #[serde(default)]
+        header: String,
+        file: String,
+        #[serde(default)]
+        footer: String,
+    },
+}
+
+impl Config {
+    /// Loads `.pai.toml` from `dir`, falling back to defaults when it does not exist.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(CONFIG_FILE);
+        if !path.exists() {
+            return Ok(Config::default());
+        }
+
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+}
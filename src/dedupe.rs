@@ -0,0 +1,172 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use crate::md::{FileEntry, estimate_tokens};
+
+/// A file whose content was replaced by a reference to an identical file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Duplicate {
+    pub path: String,
+    pub original: String,
+    pub tokens_saved: usize,
+}
+
+/// Two files whose line sets overlap above the configured threshold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NearDuplicate {
+    pub first: String,
+    pub second: String,
+    pub similarity: f64,
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+pub fn duplicate_note(original: &str) -> String {
+    format!("[identical to {}; content omitted]", original)
+}
+
+/// Replaces the content of byte-identical files with a reference to the first occurrence.
+pub fn collapse_duplicates(files: &mut [FileEntry]) -> Vec<Duplicate> {
+    let mut first_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut duplicates = Vec::new();
+
+    for index in 0..files.len() {
+        let candidates = first_by_hash
+            .entry(content_hash(&files[index].content))
+            .or_default();
+
+        // Hash matches are confirmed byte for byte before collapsing
+        let original = candidates
+            .iter()
+            .copied()
+            .find(|&candidate| files[candidate].content == files[index].content);
+
+        match original {
+            Some(original) => {
+                let original_path = files[original].relative_path.clone();
+                let note = duplicate_note(&original_path);
+                // Files shorter than the note are cheaper to repeat
+                let tokens = estimate_tokens(&files[index].content);
+                let note_tokens = estimate_tokens(&note);
+                if note_tokens >= tokens {
+                    continue;
+                }
+                files[index].content = note;
+                duplicates.push(Duplicate {
+                    path: files[index].relative_path.clone(),
+                    original: original_path,
+                    tokens_saved: tokens - note_tokens,
+                });
+            }
+            None => candidates.push(index),
+        }
+    }
+
+    duplicates
+}
+
+fn line_set(content: &str) -> HashSet<&str> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Jaccard similarity of two line sets.
+fn jaccard(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / (a.len() + b.len() - shared) as f64
+}
+
+/// Finds pairs of files at least `threshold` similar, skipping collapsed duplicates.
+pub fn find_near_duplicates(
+    files: &[FileEntry],
+    duplicates: &[Duplicate],
+    threshold: f64,
+) -> Vec<NearDuplicate> {
+    let collapsed: HashSet<&str> = duplicates.iter().map(|d| d.path.as_str()).collect();
+    let candidates: Vec<(&FileEntry, HashSet<&str>)> = files
+        .iter()
+        .filter(|file| !collapsed.contains(file.relative_path.as_str()))
+        .map(|file| (file, line_set(&file.content)))
+        .filter(|(_, lines)| !lines.is_empty())
+        .collect();
+
+    let mut near = Vec::new();
+    for (i, (first, first_lines)) in candidates.iter().enumerate() {
+        for (second, second_lines) in &candidates[i + 1..] {
+            // Jaccard can never exceed the ratio of the set sizes, so skip hopeless pairs
+            let (small, large) = if first_lines.len() < second_lines.len() {
+                (first_lines.len(), second_lines.len())
+            } else {
+                (second_lines.len(), first_lines.len())
+            };
+            if (small as f64 / large as f64) < threshold {
+                continue;
+            }
+
+            let similarity = jaccard(first_lines, second_lines);
+            if similarity >= threshold {
+                near.push(NearDuplicate {
+                    first: first.relative_path.clone(),
+                    second: second.relative_path.clone(),
+                    similarity,
+                });
+            }
+        }
+    }
+
+    near
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_duplicates_keeps_first_copy() {
+        let content = "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n".repeat(10);
+        let mut files = vec![
+            FileEntry::fixture("a/Cargo.toml", &content),
+            FileEntry::fixture("b/other.toml", "x = 1"),
+            FileEntry::fixture("c/Cargo.toml", &content),
+            // Shorter than the note, so left as is
+            FileEntry::fixture("d/other.toml", "x = 1"),
+        ];
+
+        let duplicates = collapse_duplicates(&mut files);
+
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].path, "c/Cargo.toml");
+        assert_eq!(duplicates[0].original, "a/Cargo.toml");
+        assert!(duplicates[0].tokens_saved > 0);
+        assert_eq!(files[0].content, content);
+        assert_eq!(files[2].content, duplicate_note("a/Cargo.toml"));
+        assert_eq!(files[3].content, "x = 1");
+    }
+
+    #[test]
+    fn test_find_near_duplicates() {
+        let files = vec![
+            FileEntry::fixture("a.rs", "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}"),
+            FileEntry::fixture("b.rs", "fn a() {}\nfn b() {}\nfn c() {}\nfn e() {}"),
+            FileEntry::fixture("c.rs", "struct Unrelated;"),
+        ];
+
+        let near = find_near_duplicates(&files, &[], 0.5);
+
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].first, "a.rs");
+        assert_eq!(near[0].second, "b.rs");
+        assert!((near[0].similarity - 0.6).abs() < 1e-9);
+    }
+}
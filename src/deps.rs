@@ -0,0 +1,399 @@
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Component, Path, PathBuf};
+use std::sync::LazyLock;
+
+use crate::md::FileEntry;
+
+static RUST_MOD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_]\w*)\s*;").unwrap()
+});
+static RUST_USE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(crate|super|self)((?:::[A-Za-z_]\w*)*)(?:::\{([^}]*)\})?").unwrap()
+});
+static PY_IMPORT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?m)^\s*import\s+([\w.]+(?:\s*,\s*[\w.]+)*)").unwrap());
+static PY_FROM: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*from\s+(\.*)([\w.]*)\s+import\s+(?:\(([^)]*)\)|([\w \t,*]+))").unwrap()
+});
+static JS_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:\bfrom\s*|\bimport\s*\(?\s*|\brequire\s*\(\s*)['"](\.{1,2}/[^'"]+)['"]"#)
+        .unwrap()
+});
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+/// Lexically normalises a relative path, resolving `.` and `..` components.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Index of scanned files keyed by their normalised relative path.
+struct FileIndex<'a> {
+    files: HashMap<PathBuf, &'a FileEntry>,
+}
+
+impl<'a> FileIndex<'a> {
+    fn new(files: &'a [FileEntry]) -> Self {
+        FileIndex {
+            files: files
+                .iter()
+                .map(|f| (normalize(Path::new(&f.relative_path)), f))
+                .collect(),
+        }
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn first_existing(&self, candidates: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+        candidates
+            .into_iter()
+            .map(|c| normalize(&c))
+            .find(|c| self.contains(c))
+    }
+}
+
+fn rust_module_dir(file: &Path) -> PathBuf {
+    let parent = file.parent().unwrap_or(Path::new("")).to_path_buf();
+    match file.file_name().and_then(|n| n.to_str()) {
+        Some("main.rs" | "lib.rs" | "mod.rs") => parent,
+        _ => parent.join(file.file_stem().unwrap_or_default()),
+    }
+}
+
+fn rust_crate_root(file: &Path, index: &FileIndex) -> Option<PathBuf> {
+    file.ancestors().skip(1).find_map(|dir| {
+        ["lib.rs", "main.rs"]
+            .iter()
+            .any(|root| index.contains(&dir.join(root)))
+            .then(|| dir.to_path_buf())
+    })
+}
+
+/// Resolves a module path below `base`, trying the longest prefix first so
+/// `a::b::Item` maps to `a/b.rs` when it exists and to `a.rs` otherwise.
+fn rust_resolve(base: &Path, segments: &[&str], index: &FileIndex) -> Option<PathBuf> {
+    (1..=segments.len()).rev().find_map(|len| {
+        let module = segments[..len].iter().collect::<PathBuf>();
+        index.first_existing([
+            base.join(&module).with_extension("rs"),
+            base.join(&module).join("mod.rs"),
+        ])
+    })
+}
+
+fn rust_dependencies(file: &Path, content: &str, index: &FileIndex) -> Vec<PathBuf> {
+    let module_dir = rust_module_dir(file);
+    let mut deps = Vec::new();
+
+    for capture in RUST_MOD.captures_iter(content) {
+        let name = &capture[1];
+        deps.extend(index.first_existing([
+            module_dir.join(format!("{}.rs", name)),
+            module_dir.join(name).join("mod.rs"),
+        ]));
+    }
+
+    for capture in RUST_USE.captures_iter(content) {
+        let base = match &capture[1] {
+            "crate" => match rust_crate_root(file, index) {
+                Some(root) => root,
+                None => continue,
+            },
+            "super" => module_dir.parent().unwrap_or(Path::new("")).to_path_buf(),
+            _ => module_dir.clone(),
+        };
+        let prefix: Vec<&str> = capture[2].split("::").filter(|s| !s.is_empty()).collect();
+
+        match capture.get(3) {
+            Some(group) => {
+                for item in group.as_str().split(',') {
+                    let mut segments = prefix.clone();
+                    segments.extend(item.trim().split("::").filter(|s| !s.is_empty()));
+                    deps.extend(rust_resolve(&base, &segments, index));
+                }
+            }
+            None => deps.extend(rust_resolve(&base, &prefix, index)),
+        }
+
+        // `super::Item` depends on the parent module's own file
+        if &capture[1] == "super" && capture[2].matches("::").count() <= 1 {
+            deps.extend(index.first_existing([base.with_extension("rs"), base.join("mod.rs")]));
+        }
+    }
+
+    deps
+}
+
+fn python_candidates(base: &Path, module: &str) -> [PathBuf; 2] {
+    let path = module.split('.').collect::<PathBuf>();
+    [path.with_extension("py"), path.join("__init__.py")].map(|p| base.join(p))
+}
+
+fn python_dependencies(file: &Path, content: &str, index: &FileIndex) -> Vec<PathBuf> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut deps = Vec::new();
+
+    for capture in PY_IMPORT.captures_iter(content) {
+        for module in capture[1].split(',') {
+            let module = module.trim();
+            deps.extend(index.first_existing(python_candidates(Path::new(""), module)));
+            deps.extend(index.first_existing(python_candidates(dir, module)));
+        }
+    }
+
+    for capture in PY_FROM.captures_iter(content) {
+        let dots = capture[1].len();
+        let module = &capture[2];
+        let bases: Vec<PathBuf> = if dots > 0 {
+            let mut base = dir.to_path_buf();
+            for _ in 1..dots {
+                base.pop();
+            }
+            vec![base]
+        } else {
+            vec![PathBuf::new(), dir.to_path_buf()]
+        };
+
+        for base in bases {
+            let package = module
+                .split('.')
+                .filter(|s| !s.is_empty())
+                .collect::<PathBuf>();
+            if !module.is_empty() {
+                deps.extend(index.first_existing(python_candidates(&base, module)));
+            }
+            // `from package import submodule` may name modules rather than items
+            let names = capture.get(3).or(capture.get(4)).map_or("", |m| m.as_str());
+            for name in names.split(',') {
+                // `name as alias` imports the module `name`
+                let Some(name) = name.split_whitespace().next() else {
+                    continue;
+                };
+                if name == "*" {
+                    continue;
+                }
+                deps.extend(index.first_existing([
+                    base.join(&package).join(format!("{}.py", name)),
+                    base.join(&package).join(name).join("__init__.py"),
+                ]));
+            }
+        }
+    }
+
+    deps
+}
+
+fn js_dependencies(file: &Path, content: &str, index: &FileIndex) -> Vec<PathBuf> {
+    let dir = file.parent().unwrap_or(Path::new(""));
+    let mut deps = Vec::new();
+
+    for capture in JS_IMPORT.captures_iter(content) {
+        let target = dir.join(&capture[1]);
+        let mut candidates = vec![target.clone()];
+        for ext in JS_EXTENSIONS {
+            let mut with_ext = target.clone().into_os_string();
+            with_ext.push(format!(".{}", ext));
+            candidates.push(with_ext.into());
+        }
+        for ext in JS_EXTENSIONS {
+            candidates.push(target.join(format!("index.{}", ext)));
+        }
+        deps.extend(index.first_existing(candidates));
+    }
+
+    deps
+}
+
+/// Local files referenced by `file`, based on its language's import syntax.
+fn dependencies(file: &Path, content: &str, index: &FileIndex) -> Vec<PathBuf> {
+    let ext = file.extension().and_then(|e| e.to_str()).unwrap_or("");
+    let mut deps = match ext {
+        "rs" => rust_dependencies(file, content, index),
+        "py" => python_dependencies(file, content, index),
+        _ if JS_EXTENSIONS.contains(&ext) => js_dependencies(file, content, index),
+        _ => Vec::new(),
+    };
+
+    let mut seen = HashSet::new();
+    deps.retain(|dep| dep != file && seen.insert(dep.clone()));
+    deps
+}
+
+/// Maps an entry argument to a scanned relative path. Entries may be given
+/// relative to the input directory or including it.
+fn entry_path(entry: &str, input_dir: &str, index: &FileIndex) -> Result<PathBuf> {
+    let entry = normalize(Path::new(entry));
+    let stripped = entry
+        .strip_prefix(normalize(Path::new(input_dir)))
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|_| entry.clone());
+
+    if index.contains(&stripped) {
+        Ok(stripped)
+    } else if index.contains(&entry) {
+        Ok(entry)
+    } else {
+        bail!(
+            "Entry file '{}' is not among the scanned files",
+            entry.display()
+        )
+    }
+}
+
+/// Starting from `entries`, follows local imports up to `max_depth` levels and
+/// returns the reached files ordered so that dependencies precede dependents.
+pub fn follow_dependencies(
+    files: &[FileEntry],
+    entries: &[String],
+    input_dir: &str,
+    max_depth: usize,
+) -> Result<Vec<PathBuf>> {
+    let index = FileIndex::new(files);
+    let mut graph: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+    let mut queue: VecDeque<(PathBuf, usize)> = VecDeque::new();
+    let mut roots = Vec::new();
+
+    for entry in entries {
+        let path = entry_path(entry, input_dir, &index)?;
+        roots.push(path.clone());
+        queue.push_back((path, 0));
+    }
+
+    while let Some((path, depth)) = queue.pop_front() {
+        if graph.contains_key(&path) {
+            continue;
+        }
+        let deps = if depth < max_depth {
+            dependencies(&path, &index.files[&path].content, &index)
+        } else {
+            Vec::new()
+        };
+        for dep in &deps {
+            if !graph.contains_key(dep) {
+                queue.push_back((dep.clone(), depth + 1));
+            }
+        }
+        graph.insert(path, deps);
+    }
+
+    // Depth-first post-order over the reached graph; cycles are broken at the
+    // first revisit so every file appears exactly once.
+    let mut order = Vec::new();
+    let mut visited = HashSet::new();
+    for root in roots {
+        visit(&root, &graph, &mut visited, &mut order);
+    }
+    Ok(order)
+}
+
+fn visit(
+    path: &PathBuf,
+    graph: &HashMap<PathBuf, Vec<PathBuf>>,
+    visited: &mut HashSet<PathBuf>,
+    order: &mut Vec<PathBuf>,
+) {
+    if !visited.insert(path.clone()) {
+        return;
+    }
+    for dep in graph.get(path).into_iter().flatten() {
+        if graph.contains_key(dep) {
+            visit(dep, graph, visited, order);
+        }
+    }
+    order.push(path.clone());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn follow(files: &[FileEntry], entries: &[&str], depth: usize) -> Vec<String> {
+        let entries: Vec<String> = entries.iter().map(|e| e.to_string()).collect();
+        follow_dependencies(files, &entries, ".", depth)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_mod_and_use() {
+        let files = vec![
+            FileEntry::fixture("src/main.rs", "mod models;\nmod md;\nfn main() {}"),
+            FileEntry::fixture(
+                "src/md.rs",
+                "use crate::models::{claude::Claude, AppError};",
+            ),
+            FileEntry::fixture("src/models/mod.rs", "pub mod claude;\npub struct AppError;"),
+            FileEntry::fixture("src/models/claude.rs", "use super::AppError;"),
+            FileEntry::fixture("src/unused.rs", ""),
+        ];
+
+        assert_eq!(
+            follow(&files, &["src/main.rs"], 10),
+            vec![
+                "src/models/claude.rs",
+                "src/models/mod.rs",
+                "src/md.rs",
+                "src/main.rs"
+            ]
+        );
+        assert_eq!(
+            follow(&files, &["./src/md.rs"], 1),
+            vec!["src/models/claude.rs", "src/models/mod.rs", "src/md.rs"]
+        );
+    }
+
+    #[test]
+    fn test_python_imports() {
+        let files = vec![
+            FileEntry::fixture("app.py", "import util\nfrom pkg import helpers\n"),
+            FileEntry::fixture("util.py", ""),
+            FileEntry::fixture("pkg/__init__.py", ""),
+            FileEntry::fixture("pkg/helpers.py", "from .core import run\n"),
+            FileEntry::fixture("pkg/core.py", ""),
+        ];
+
+        assert_eq!(
+            follow(&files, &["app.py"], 10),
+            vec![
+                "util.py",
+                "pkg/__init__.py",
+                "pkg/core.py",
+                "pkg/helpers.py",
+                "app.py"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_js_relative_imports_and_cycles() {
+        let files = vec![
+            FileEntry::fixture(
+                "src/index.ts",
+                "import { a } from './a';\nimport React from 'react';",
+            ),
+            FileEntry::fixture("src/a.ts", "export * from '../lib';\nimport './index';"),
+            FileEntry::fixture("lib/index.js", "module.exports = {};"),
+        ];
+
+        assert_eq!(
+            follow(&files, &["src/index.ts"], 10),
+            vec!["lib/index.js", "src/a.ts", "src/index.ts"]
+        );
+    }
+}
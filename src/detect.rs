@@ -0,0 +1,264 @@
+use std::path::Path;
+
+/// Number of leading bytes inspected when deciding whether a file is binary.
+pub const SNIFF_LEN: usize = 8192;
+
+/// A line at least this long suggests minified or machine-written content.
+pub const MINIFIED_LINE_LENGTH: usize = 1000;
+
+/// Average line length above which a file with a very long line counts as minified.
+const MINIFIED_AVERAGE_LENGTH: usize = 200;
+
+/// Generated-code markers are only looked for in this many leading lines.
+const GENERATED_HEADER_LINES: usize = 10;
+
+const MAGIC_NUMBERS: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"\x00\x00\x01\x00", "ICO image"),
+    (b"OggS", "Ogg media"),
+    (b"fLaC", "FLAC audio"),
+    (b"wOFF", "WOFF font"),
+    (b"wOF2", "WOFF2 font"),
+    (b"%PDF-", "PDF document"),
+    (b"PK\x03\x04", "zip archive"),
+    (b"\x1f\x8b", "gzip data"),
+    (b"\xfd7zXZ\x00", "xz data"),
+    (b"7z\xbc\xaf\x27\x1c", "7z archive"),
+    (b"\x7fELF", "ELF executable"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O executable"),
+    (b"\xca\xfe\xba\xbe", "Mach-O or Java class file"),
+    (b"\x00asm", "WebAssembly module"),
+    (b"SQLite format 3\x00", "SQLite database"),
+];
+
+/// Formats stored in a RIFF container, named at bytes 8..12.
+const RIFF_FORMATS: &[(&[u8], &str)] = &[
+    (b"WAVE", "WAV audio"),
+    (b"AVI ", "AVI video"),
+    (b"WEBP", "WebP image"),
+];
+
+/// Signatures that are short printable ASCII, so plain text may start with
+/// them too. Each needs the bytes that follow to match as well.
+fn ascii_signature(prefix: &[u8]) -> Option<&'static str> {
+    if prefix.starts_with(b"RIFF") {
+        return RIFF_FORMATS
+            .iter()
+            .find(|(format, _)| prefix.get(8..12) == Some(*format))
+            .map(|(_, kind)| *kind);
+    }
+    // Block size digit, then the first block's magic
+    if prefix.starts_with(b"BZh")
+        && matches!(prefix.get(3), Some(b'1'..=b'9'))
+        && prefix.get(4..10) == Some(b"1AY&SY")
+    {
+        return Some("bzip2 data");
+    }
+    // ID3v2 tag: a major version byte, at most 4
+    if prefix.starts_with(b"ID3") && prefix.get(3).is_some_and(|&version| version < 0x05) {
+        return Some("MP3 audio");
+    }
+    None
+}
+
+/// Generated-code markers checked in a file's leading lines.
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT"];
+
+/// File name suffixes of protobuf and gRPC output.
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".pb.rs",
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    "_pb2.py",
+    "_pb2_grpc.py",
+];
+
+/// Text encodings recognised from a byte order mark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bom {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Bom {
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\xef\xbb\xbf") {
+            Some(Bom::Utf8)
+        } else if bytes.starts_with(b"\xff\xfe") {
+            Some(Bom::Utf16Le)
+        } else if bytes.starts_with(b"\xfe\xff") {
+            Some(Bom::Utf16Be)
+        } else {
+            None
+        }
+    }
+}
+
+/// Describes why `prefix` (the first [`SNIFF_LEN`] bytes of a file) looks binary.
+///
+/// Known file signatures are checked first, then null bytes and the share of
+/// control characters. Text starting with a UTF-16 byte order mark is never binary.
+pub fn sniff_binary(prefix: &[u8]) -> Option<&'static str> {
+    let prefix = &prefix[..prefix.len().min(SNIFF_LEN)];
+    if matches!(Bom::detect(prefix), Some(Bom::Utf16Le | Bom::Utf16Be)) {
+        return None;
+    }
+
+    if let Some((_, kind)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| prefix.starts_with(magic))
+    {
+        return Some(kind);
+    }
+    if let Some(kind) = ascii_signature(prefix) {
+        return Some(kind);
+    }
+    // MP4 and QuickTime keep their signature after a 4-byte box size
+    if prefix.get(4..8) == Some(b"ftyp") {
+        return Some("MP4 media");
+    }
+
+    // Check for null bytes
+    let reason = if prefix.contains(&0) {
+        "contains null bytes"
+    } else {
+        // Check for high percentage of non-printable characters
+        let non_printable_count = prefix
+            .iter()
+            .filter(|&&c| c < 32 && c != 9 && c != 10 && c != 12 && c != 13) // tab, LF, FF, CR
+            .count();
+
+        // If more than 10% of bytes are non-printable, consider it binary
+        if non_printable_count * 10 <= prefix.len() {
+            return None;
+        }
+        "mostly control characters"
+    };
+    // "MZ" alone is too short to trust, so it only names what failed the text check
+    Some(if prefix.starts_with(b"MZ") {
+        "Windows executable"
+    } else {
+        reason
+    })
+}
+
+/// Decodes UTF-8 or BOM-marked UTF-16 text, dropping the byte order mark.
+pub fn decode_text(bytes: Vec<u8>) -> Option<String> {
+    let decode_utf16 = |bytes: &[u8], from: fn([u8; 2]) -> u16| {
+        if !bytes.len().is_multiple_of(2) {
+            return None;
+        }
+        let units = bytes.chunks_exact(2).map(|pair| from([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .ok()
+    };
+
+    match Bom::detect(&bytes) {
+        Some(Bom::Utf8) => String::from_utf8(bytes[3..].to_vec()).ok(),
+        Some(Bom::Utf16Le) => decode_utf16(&bytes[2..], u16::from_le_bytes),
+        Some(Bom::Utf16Be) => decode_utf16(&bytes[2..], u16::from_be_bytes),
+        None => String::from_utf8(bytes).ok(),
+    }
+}
+
+/// Returns the longest line length if `content` looks minified.
+///
+/// `.min.js`, `.min.css` and `.map` files always count; other files need a line
+/// of at least [`MINIFIED_LINE_LENGTH`] bytes and long lines on average.
+pub fn minified_line_length(path: &Path, content: &str) -> Option<usize> {
+    let longest = content.lines().map(str::len).max().unwrap_or(0);
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    if name.contains(".min.") || name.ends_with(".map") {
+        return Some(longest);
+    }
+
+    let lines = content.lines().count().max(1);
+    (longest >= MINIFIED_LINE_LENGTH && content.len() / lines >= MINIFIED_AVERAGE_LENGTH)
+        .then_some(longest)
+}
+
+/// Returns the marker or file name pattern identifying generated code, if any.
+pub fn generated_marker(path: &Path, content: &str) -> Option<&'static str> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    if let Some(suffix) = GENERATED_SUFFIXES.iter().find(|s| name.ends_with(*s)) {
+        return Some(suffix);
+    }
+
+    content
+        .lines()
+        .take(GENERATED_HEADER_LINES)
+        .find_map(|line| GENERATED_MARKERS.iter().find(|m| line.contains(*m)))
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_binary_and_utf16() {
+        assert_eq!(sniff_binary(b"\x89PNG\r\n\x1a\nrest"), Some("PNG image"));
+        assert_eq!(sniff_binary(b"\0\0\0\x18ftypmp42"), Some("MP4 media"));
+        assert_eq!(sniff_binary(b"text\0more"), Some("contains null bytes"));
+        assert_eq!(sniff_binary(b"fn main() {}\n"), None);
+
+        // Text that happens to start like a short ASCII signature
+        for text in ["MZ notes\n", "RIFF cases\n", "BZh9 draft\n", "ID3 tags\n"] {
+            assert_eq!(sniff_binary(text.as_bytes()), None, "{}", text);
+        }
+        assert_eq!(sniff_binary(b"RIFF\x24\0\0\0WAVEfmt "), Some("WAV audio"));
+        assert_eq!(sniff_binary(b"BZh91AY&SY\x01"), Some("bzip2 data"));
+        assert_eq!(sniff_binary(b"ID3\x04\0\0"), Some("MP3 audio"));
+        assert_eq!(sniff_binary(b"MZ\x90\0\x03\0"), Some("Windows executable"));
+
+        let utf16: Vec<u8> = [0xff, 0xfe]
+            .into_iter()
+            .chain("hi\n".encode_utf16().flat_map(u16::to_le_bytes))
+            .collect();
+        assert_eq!(sniff_binary(&utf16), None);
+        assert_eq!(decode_text(utf16).as_deref(), Some("hi\n"));
+        assert_eq!(
+            decode_text(b"\xef\xbb\xbfok".to_vec()).as_deref(),
+            Some("ok")
+        );
+    }
+
+    #[test]
+    fn test_minified_and_generated() {
+        let bundle = format!("!function(){{{}}}();", "a=1;".repeat(400));
+        assert_eq!(
+            minified_line_length(Path::new("app.js"), &bundle),
+            Some(bundle.len())
+        );
+        assert_eq!(
+            minified_line_length(Path::new("app.js"), "let a = 1;\n"),
+            None
+        );
+        assert!(minified_line_length(Path::new("app.js.map"), "{}").is_some());
+
+        assert_eq!(
+            generated_marker(Path::new("api.pb.rs"), "pub struct A;"),
+            Some(".pb.rs")
+        );
+        assert_eq!(
+            generated_marker(
+                Path::new("types.go"),
+                "// Code generated by stringer. DO NOT EDIT.\n"
+            ),
+            Some("DO NOT EDIT")
+        );
+        assert_eq!(generated_marker(Path::new("lib.rs"), "fn a() {}"), None);
+    }
+}
@@ -0,0 +1,312 @@
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::md::FileEntry;
+
+/// Converts a file's raw text into a more compact representation for packing.
+///
+/// Extractors are consulted in registration order; the first one whose
+/// `matches` returns true handles the file. If extraction fails, the raw
+/// content is packed unchanged.
+pub trait Extractor: Send + Sync {
+    /// Short identifier shown in progress output.
+    fn name(&self) -> &'static str;
+
+    fn matches(&self, path: &Path) -> bool;
+
+    fn extract(&self, path: &Path, content: &str) -> Result<String>;
+}
+
+#[derive(Default)]
+pub struct ExtractorRegistry {
+    extractors: Vec<Box<dyn Extractor>>,
+}
+
+impl ExtractorRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in notebook, CSV and lockfile extractors.
+    pub fn with_defaults(notebook_outputs: bool, csv_rows: usize) -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(NotebookExtractor {
+            include_outputs: notebook_outputs,
+        }));
+        registry.register(Box::new(CsvExtractor { rows: csv_rows }));
+        registry.register(Box::new(LockfileExtractor));
+        registry
+    }
+
+    pub fn register(&mut self, extractor: Box<dyn Extractor>) {
+        self.extractors.push(extractor);
+    }
+
+    /// Rewrites matching files in place, returning `(path, extractor)` for each one converted.
+    pub fn apply(&self, files: &mut [FileEntry]) -> Vec<(String, &'static str)> {
+        let mut applied = Vec::new();
+        for file in files {
+            let Some(extractor) = self.extractors.iter().find(|e| e.matches(&file.path)) else {
+                continue;
+            };
+            match extractor.extract(&file.path, &file.content) {
+                Ok(content) => {
+                    file.content = content;
+                    applied.push((file.relative_path.clone(), extractor.name()));
+                }
+                Err(err) => eprintln!(
+                    "Warning: {} extractor failed for {}: {}. Packing raw content.",
+                    extractor.name(),
+                    file.relative_path,
+                    err
+                ),
+            }
+        }
+        applied
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| extensions.iter().any(|x| e.eq_ignore_ascii_case(x)))
+}
+
+/// Notebook fields may be a string or an array of line strings.
+fn joined_text(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(lines)) => lines.iter().filter_map(Value::as_str).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Turns `.ipynb` JSON into ordered code and markdown cells, dropping images.
+pub struct NotebookExtractor {
+    pub include_outputs: bool,
+}
+
+impl NotebookExtractor {
+    fn outputs(&self, cell: &Value) -> Vec<String> {
+        let mut outputs = Vec::new();
+        for output in cell["outputs"].as_array().into_iter().flatten() {
+            match output["output_type"].as_str() {
+                Some("stream") => outputs.push(joined_text(output.get("text"))),
+                Some("execute_result" | "display_data") => {
+                    let data = &output["data"];
+                    if data.get("text/plain").is_some() {
+                        outputs.push(joined_text(data.get("text/plain")));
+                    } else if let Some(data) = data.as_object() {
+                        for mime in data.keys() {
+                            outputs.push(format!("[{} output omitted]", mime));
+                        }
+                    }
+                }
+                Some("error") => outputs.push(format!(
+                    "{}: {}",
+                    output["ename"].as_str().unwrap_or("Error"),
+                    output["evalue"].as_str().unwrap_or("")
+                )),
+                _ => {}
+            }
+        }
+        outputs
+    }
+}
+
+impl Extractor for NotebookExtractor {
+    fn name(&self) -> &'static str {
+        "notebook"
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        has_extension(path, &["ipynb"])
+    }
+
+    fn extract(&self, _path: &Path, content: &str) -> Result<String> {
+        let notebook: Value = serde_json::from_str(content).context("Invalid notebook JSON")?;
+        let Some(cells) = notebook["cells"].as_array() else {
+            bail!("Notebook has no cells");
+        };
+
+        let mut sections = Vec::new();
+        for (index, cell) in cells.iter().enumerate() {
+            let cell_type = cell["cell_type"].as_str().unwrap_or("raw");
+            let source = joined_text(cell.get("source"));
+            let mut section = format!("[{} cell {}]\n{}", cell_type, index + 1, source.trim_end());
+
+            if self.include_outputs && cell_type == "code" {
+                for output in self.outputs(cell) {
+                    section.push_str(&format!("\n[output]\n{}", output.trim_end()));
+                }
+            }
+            sections.push(section);
+        }
+
+        Ok(sections.join("\n\n"))
+    }
+}
+
+/// Keeps the header and first rows of `.csv`/`.tsv` files.
+pub struct CsvExtractor {
+    pub rows: usize,
+}
+
+impl Extractor for CsvExtractor {
+    fn name(&self) -> &'static str {
+        "csv"
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        has_extension(path, &["csv", "tsv"])
+    }
+
+    fn extract(&self, _path: &Path, content: &str) -> Result<String> {
+        let lines: Vec<&str> = content.lines().collect();
+        let kept = lines.len().min(self.rows + 1);
+        let mut preview = lines[..kept].join("\n");
+        if lines.len() > kept {
+            preview.push_str(&format!("\n... ({} more rows)", lines.len() - kept));
+        }
+        Ok(preview)
+    }
+}
+
+/// Summarises lockfiles as a list of resolved packages and versions.
+pub struct LockfileExtractor;
+
+impl LockfileExtractor {
+    fn toml_packages(content: &str) -> Result<Vec<(String, String)>> {
+        let lock: toml::Value = toml::from_str(content).context("Invalid lockfile TOML")?;
+        Ok(lock
+            .get("package")
+            .and_then(|p| p.as_array())
+            .into_iter()
+            .flatten()
+            .map(|package| {
+                let field = |key: &str| {
+                    package
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("?")
+                        .to_string()
+                };
+                (field("name"), field("version"))
+            })
+            .collect())
+    }
+
+    fn npm_packages(content: &str) -> Result<Vec<(String, String)>> {
+        let lock: Value = serde_json::from_str(content).context("Invalid package-lock JSON")?;
+        let version = |info: &Value| info["version"].as_str().unwrap_or("?").to_string();
+
+        if let Some(packages) = lock["packages"].as_object() {
+            Ok(packages
+                .iter()
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, info)| {
+                    let name = key.rsplit("node_modules/").next().unwrap_or(key);
+                    (name.to_string(), version(info))
+                })
+                .collect())
+        } else {
+            Ok(lock["dependencies"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(name, info)| (name.clone(), version(info)))
+                .collect())
+        }
+    }
+}
+
+impl Extractor for LockfileExtractor {
+    fn name(&self) -> &'static str {
+        "lockfile"
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        matches!(
+            path.file_name().and_then(|n| n.to_str()),
+            Some("Cargo.lock" | "poetry.lock" | "package-lock.json")
+        )
+    }
+
+    fn extract(&self, path: &Path, content: &str) -> Result<String> {
+        let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let packages = if file_name == "package-lock.json" {
+            Self::npm_packages(content)?
+        } else {
+            Self::toml_packages(content)?
+        };
+
+        let mut summary = format!("{} resolves {} packages:", file_name, packages.len());
+        for (name, version) in packages {
+            summary.push_str(&format!("\n{} {}", name, version));
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notebook_extraction_strips_images() {
+        let notebook = r##"{
+            "cells": [
+                {"cell_type": "markdown", "source": ["# Title\n", "Intro"]},
+                {"cell_type": "code", "source": "print(1)", "outputs": [
+                    {"output_type": "stream", "text": ["1\n"]},
+                    {"output_type": "display_data", "data": {"image/png": "iVBORw0KGgo="}}
+                ]}
+            ]
+        }"##;
+        let path = Path::new("analysis.ipynb");
+
+        let without = NotebookExtractor {
+            include_outputs: false,
+        };
+        assert_eq!(
+            without.extract(path, notebook).unwrap(),
+            "[markdown cell 1]\n# Title\nIntro\n\n[code cell 2]\nprint(1)"
+        );
+
+        let with = NotebookExtractor {
+            include_outputs: true,
+        };
+        assert_eq!(
+            with.extract(path, notebook).unwrap(),
+            "[markdown cell 1]\n# Title\nIntro\n\n[code cell 2]\nprint(1)\n[output]\n1\n[output]\n[image/png output omitted]"
+        );
+    }
+
+    #[test]
+    fn test_csv_and_lockfile_extraction() {
+        let csv = CsvExtractor { rows: 2 };
+        assert_eq!(
+            csv.extract(Path::new("data.csv"), "a,b\n1,2\n3,4\n5,6\n7,8")
+                .unwrap(),
+            "a,b\n1,2\n3,4\n... (2 more rows)"
+        );
+
+        let lock = "[[package]]\nname = \"serde\"\nversion = \"1.0.0\"\nchecksum = \"abc\"\n";
+        assert_eq!(
+            LockfileExtractor
+                .extract(Path::new("Cargo.lock"), lock)
+                .unwrap(),
+            "Cargo.lock resolves 1 packages:\nserde 1.0.0"
+        );
+    }
+
+    #[test]
+    fn test_registry_falls_back_to_raw_content() {
+        let registry = ExtractorRegistry::with_defaults(false, 10);
+        let mut files = vec![FileEntry::fixture("broken.ipynb", "not json")];
+
+        assert!(registry.apply(&mut files).is_empty());
+        assert_eq!(files[0].content, "not json");
+    }
+}
@@ -0,0 +1,231 @@
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Component, Path};
+
+/// Names excluded wherever they appear in a path: build output, dependency
+/// caches, VCS metadata, editor state and secrets.
+pub const DEFAULT_EXCLUDED: &[&str] = &[
+    "target",
+    "build",
+    "dist",
+    "node_modules",
+    ".git",
+    ".venv",
+    "venv",
+    "__pycache__",
+    ".pytest_cache",
+    ".idea",
+    ".vscode",
+    ".next",
+    ".nuxt",
+    ".docusaurus",
+    ".cargo",
+    ".rustup",
+    ".env",
+    ".DS_Store",
+];
+
+/// Hidden names that are still included because they hold useful project context.
+pub const DEFAULT_ALLOWED_HIDDEN: &[&str] = &[".github", ".gitlab", ".gitlab-ci.yml", ".circleci"];
+
+/// `[filter]` section of `.pai.toml`.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FilterConfig {
+    /// Extra names to exclude in addition to the defaults.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Extra hidden names to include.
+    #[serde(default)]
+    pub allow_hidden: Vec<String>,
+    /// Include hidden files and directories (excluded names still apply).
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Filenames or extensions like ".lock" to ignore, like `--ignore`.
+    #[serde(default)]
+    pub ignore: Vec<String>,
+}
+
+/// Why a path is excluded by a [`PathFilter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterReason {
+    IgnoredName(String),
+    IgnoredExtension(String),
+    Excluded(String),
+    Hidden(String),
+}
+
+impl FilterReason {
+    /// Short, stable identifier of the rule.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            FilterReason::IgnoredName(_) => "ignore-pattern",
+            FilterReason::IgnoredExtension(_) => "ignored-extension",
+            FilterReason::Excluded(_) => "excluded-name",
+            FilterReason::Hidden(_) => "hidden",
+        }
+    }
+}
+
+impl fmt::Display for FilterReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterReason::IgnoredName(name) => write!(f, "'{}' matches an ignore pattern", name),
+            FilterReason::IgnoredExtension(ext) => write!(f, "extension '{}' is ignored", ext),
+            FilterReason::Excluded(name) => write!(f, "'{}' is excluded by default", name),
+            FilterReason::Hidden(name) => write!(f, "'{}' is hidden", name),
+        }
+    }
+}
+
+/// Decides which paths are skipped by both the packer and the agent tools.
+///
+/// Paths are matched component by component, so `a/b`, `a\b` and `./a/b`
+/// behave the same; `.` and `..` components are ignored.
+#[derive(Debug, Clone)]
+pub struct PathFilter {
+    excluded: Vec<String>,
+    allowed_hidden: Vec<String>,
+    include_hidden: bool,
+    ignore_names: Vec<String>,
+    ignore_extensions: Vec<String>,
+}
+
+impl Default for PathFilter {
+    fn default() -> Self {
+        PathFilter {
+            excluded: DEFAULT_EXCLUDED.iter().map(|s| s.to_string()).collect(),
+            allowed_hidden: DEFAULT_ALLOWED_HIDDEN
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            include_hidden: false,
+            ignore_names: Vec::new(),
+            ignore_extensions: Vec::new(),
+        }
+    }
+}
+
+impl PathFilter {
+    pub fn from_config(config: &FilterConfig) -> Self {
+        let mut filter = PathFilter::default();
+        filter.excluded.extend(config.exclude.iter().cloned());
+        filter
+            .allowed_hidden
+            .extend(config.allow_hidden.iter().cloned());
+        filter.include_hidden = config.include_hidden;
+        filter.with_ignore(&config.ignore)
+    }
+
+    /// Adds ignore patterns: `.ext` entries ignore an extension, anything else a name.
+    pub fn with_ignore(mut self, patterns: &[String]) -> Self {
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            // A leading dot with no other dots reads as an extension (".lock"),
+            // but the pattern is also kept as a name so ".env" still matches the file
+            if pattern.starts_with('.') && !pattern[1..].contains('.') {
+                self.ignore_extensions.push(pattern.to_string());
+            }
+            self.ignore_names.push(pattern.to_string());
+        }
+        self
+    }
+
+    fn component_reason(&self, name: &str) -> Option<FilterReason> {
+        if self.ignore_names.iter().any(|p| p == name) {
+            return Some(FilterReason::IgnoredName(name.to_string()));
+        }
+        if self.excluded.iter().any(|p| p == name) {
+            return Some(FilterReason::Excluded(name.to_string()));
+        }
+        if name.starts_with('.')
+            && !self.include_hidden
+            && !self.allowed_hidden.iter().any(|p| p == name)
+        {
+            return Some(FilterReason::Hidden(name.to_string()));
+        }
+        None
+    }
+
+    /// Explains why `path` is skipped, or returns `None` if it is included.
+    pub fn explain(&self, path: &Path) -> Option<FilterReason> {
+        let names: Vec<String> = path
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+                _ => None,
+            })
+            // Treat backslashes as separators so Windows-style paths match everywhere
+            .flat_map(|name| {
+                name.split('\\')
+                    .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        for name in &names {
+            if let Some(reason) = self.component_reason(name) {
+                return Some(reason);
+            }
+        }
+
+        let file_name = names.last()?;
+        let ext = Path::new(file_name).extension()?;
+        let ext = format!(".{}", ext.to_string_lossy());
+        self.ignore_extensions
+            .contains(&ext)
+            .then_some(FilterReason::IgnoredExtension(ext))
+    }
+
+    pub fn is_skipped(&self, path: &Path) -> bool {
+        self.explain(path).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_rules() {
+        let filter = PathFilter::default();
+        assert!(filter.is_skipped(Path::new("./.git/config")));
+        assert!(filter.is_skipped(Path::new("app/node_modules/pkg/index.js")));
+        assert!(filter.is_skipped(Path::new(".env")));
+        assert!(!filter.is_skipped(Path::new("../project/src/main.rs")));
+        assert!(!filter.is_skipped(Path::new(".github/workflows/ci.yml")));
+        assert_eq!(
+            filter.explain(Path::new("src/.hidden")),
+            Some(FilterReason::Hidden(".hidden".to_string()))
+        );
+        assert_eq!(
+            filter.explain(Path::new(r"project\target\debug\app.rs")),
+            Some(FilterReason::Excluded("target".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_ignore_patterns_and_config() {
+        let config = FilterConfig {
+            exclude: vec!["coverage".to_string()],
+            allow_hidden: vec![".config".to_string()],
+            include_hidden: false,
+            ignore: vec!["Cargo.lock".to_string()],
+        };
+        let filter = PathFilter::from_config(&config).with_ignore(&[".log".to_string()]);
+
+        assert_eq!(
+            filter.explain(Path::new("Cargo.lock")),
+            Some(FilterReason::IgnoredName("Cargo.lock".to_string()))
+        );
+        assert_eq!(
+            filter.explain(Path::new("logs/app.log")),
+            Some(FilterReason::IgnoredExtension(".log".to_string()))
+        );
+        assert_eq!(
+            filter.explain(Path::new("coverage/index.html")),
+            Some(FilterReason::Excluded("coverage".to_string()))
+        );
+        assert!(!filter.is_skipped(Path::new(".config/settings.toml")));
+    }
+}
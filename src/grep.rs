@@ -0,0 +1,161 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use regex::Regex;
+
+/// How multiple `--grep` patterns combine.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GrepMode {
+    /// Keep files matching any pattern.
+    #[default]
+    Any,
+    /// Keep files matching every pattern.
+    All,
+}
+
+/// Selects files by content and optionally trims them to the matching regions.
+#[derive(Debug)]
+pub struct GrepFilter {
+    patterns: Vec<Regex>,
+    mode: GrepMode,
+    context: Option<usize>,
+}
+
+impl GrepFilter {
+    pub fn new(patterns: &[String], mode: GrepMode, context: Option<usize>) -> Result<Self> {
+        let patterns = patterns
+            .iter()
+            .map(|p| Regex::new(p).with_context(|| format!("Invalid --grep pattern: {}", p)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(GrepFilter {
+            patterns,
+            mode,
+            context,
+        })
+    }
+
+    pub fn matches(&self, content: &str) -> bool {
+        match self.mode {
+            GrepMode::Any => self.patterns.iter().any(|p| p.is_match(content)),
+            GrepMode::All => self.patterns.iter().all(|p| p.is_match(content)),
+        }
+    }
+
+    /// Returns the content to pack: the whole file, or only the matching
+    /// regions when a context size was requested.
+    pub fn select(&self, content: &str) -> String {
+        match self.context {
+            Some(context) => self.excerpt(content, context),
+            None => content.to_string(),
+        }
+    }
+
+    /// Matching lines plus `context` lines around them, with overlapping
+    /// regions merged and each region headed by its 1-based line range.
+    ///
+    /// Matches are also found in the whole content, so a pattern spanning
+    /// several lines selects all of them. Falls back to the whole file if no
+    /// region is found.
+    fn excerpt(&self, content: &str, context: usize) -> String {
+        let lines: Vec<&str> = content.lines().collect();
+        if lines.is_empty() {
+            return content.to_string();
+        }
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        let line_of = |offset: usize| {
+            (line_starts.partition_point(|&start| start <= offset) - 1).min(lines.len() - 1)
+        };
+
+        // Lines matching on their own (so `^` and `$` work per line), plus the
+        // lines covered by matches in the whole content.
+        let single = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| self.patterns.iter().any(|p| p.is_match(line)))
+            .map(|(index, _)| (index, index));
+        let mut matched: Vec<(usize, usize)> = self
+            .patterns
+            .iter()
+            .flat_map(|p| p.find_iter(content))
+            .map(|m| {
+                (
+                    line_of(m.start()),
+                    line_of(m.end().saturating_sub(1).max(m.start())),
+                )
+            })
+            .chain(single)
+            .collect();
+        matched.sort_unstable();
+
+        let mut regions: Vec<(usize, usize)> = Vec::new();
+        for (first, last) in matched {
+            let start = first.saturating_sub(context);
+            let end = (last + context).min(lines.len() - 1);
+            match regions.last_mut() {
+                Some(region) if start <= region.1 + 1 => region.1 = region.1.max(end),
+                _ => regions.push((start, end)),
+            }
+        }
+        if regions.is_empty() {
+            return content.to_string();
+        }
+
+        regions
+            .iter()
+            .map(|&(start, end)| {
+                format!(
+                    "... lines {}-{} ...\n{}",
+                    start + 1,
+                    end + 1,
+                    lines[start..=end].join("\n")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(patterns: &[&str], mode: GrepMode, context: Option<usize>) -> GrepFilter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        GrepFilter::new(&patterns, mode, context).unwrap()
+    }
+
+    #[test]
+    fn test_grep_modes() {
+        let content = "fn parse() {}\nfn render() {}";
+        assert!(filter(&["parse", "missing"], GrepMode::Any, None).matches(content));
+        assert!(!filter(&["parse", "missing"], GrepMode::All, None).matches(content));
+        assert!(filter(&["parse", "render"], GrepMode::All, None).matches(content));
+    }
+
+    #[test]
+    fn test_grep_excerpt_merges_regions() {
+        let content = "a\nb\nmatch\nc\nd\ne\nf\nmatch\ng";
+        let grep = filter(&["match"], GrepMode::Any, Some(1));
+        assert_eq!(
+            grep.select(content),
+            "... lines 2-4 ...\nb\nmatch\nc\n... lines 7-9 ...\nf\nmatch\ng"
+        );
+
+        let wide = filter(&["match"], GrepMode::Any, Some(2));
+        assert_eq!(
+            wide.select(content),
+            "... lines 1-9 ...\na\nb\nmatch\nc\nd\ne\nf\nmatch\ng"
+        );
+
+        // A match spanning lines selects every line it covers.
+        let multi_line = filter(&[r"c\nd"], GrepMode::Any, Some(0));
+        assert!(multi_line.matches(content));
+        assert_eq!(multi_line.select(content), "... lines 4-5 ...\nc\nd");
+        let anchored = filter(&["^match"], GrepMode::Any, Some(0));
+        assert_eq!(
+            anchored.select(content),
+            "... lines 3-3 ...\nmatch\n... lines 8-8 ...\nmatch"
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::{Context, Result, bail};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::md::FileEntry;
+
+/// Separates commits in `git log` output.
+const RECORD_SEPARATOR: char = '\x1e';
+/// Separates fields of a commit header.
+const FIELD_SEPARATOR: char = '\x1f';
+
+/// A commit that touched at least one packed file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    pub hash: String,
+    pub author: String,
+    pub date: String,
+    pub subject: String,
+    /// Packed files changed by the commit, relative to the scan root.
+    pub files: Vec<String>,
+}
+
+impl Commit {
+    fn parse_header(line: &str) -> Option<Self> {
+        let mut fields = line.strip_prefix(RECORD_SEPARATOR)?.split(FIELD_SEPARATOR);
+        Some(Commit {
+            hash: fields.next()?.to_string(),
+            author: fields.next()?.to_string(),
+            date: fields.next()?.to_string(),
+            subject: fields.next().unwrap_or("").to_string(),
+            files: Vec::new(),
+        })
+    }
+}
+
+/// Recent commits for the packed files and the commit that last changed each one.
+#[derive(Debug, Default)]
+pub struct GitHistory {
+    pub commits: Vec<Commit>,
+    last_changed: HashMap<String, Commit>,
+}
+
+fn git(repo: &Path) -> Command {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(repo);
+    // Print non-ASCII paths verbatim so they match the scanned paths
+    command.args(["-c", "core.quotePath=false"]);
+    command
+}
+
+/// Files among `paths` that git tracks, so the log walk can stop once all are seen.
+///
+/// Paths are matched after listing rather than passed as arguments, which
+/// would overflow the command line for large packs.
+fn tracked_files(repo: &Path, paths: &HashSet<&str>) -> Result<HashSet<String>> {
+    let output = git(repo)
+        .args(["ls-files", "-z"])
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git ls-files failed in {}: {}",
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| paths.contains(path))
+        .map(String::from)
+        .collect())
+}
+
+/// Whether the repository has a commit yet; `git log` fails before the first.
+fn has_commits(repo: &Path) -> Result<bool> {
+    let status = git(repo)
+        .args(["rev-parse", "-q", "--verify", "HEAD"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run git")?;
+    Ok(status.success())
+}
+
+impl GitHistory {
+    /// Reads the history of `files` from the git repository containing `repo`.
+    ///
+    /// File paths are taken relative to `repo`. The log is walked newest first:
+    /// the first `limit` commits form the history section, and walking continues
+    /// only until every tracked file has its last change.
+    pub fn load(repo: &Path, files: &[FileEntry], limit: usize) -> Result<Self> {
+        let paths: HashSet<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        let mut history = GitHistory::default();
+        if paths.is_empty() || !has_commits(repo)? {
+            return Ok(history);
+        }
+        let mut pending = tracked_files(repo, &paths)?;
+
+        let format = format!(
+            "--format={}%h{}%an{}%ad{}%s",
+            RECORD_SEPARATOR, FIELD_SEPARATOR, FIELD_SEPARATOR, FIELD_SEPARATOR
+        );
+        let mut child = git(repo)
+            .args(["log", "--date=short", "--name-only", "--relative"])
+            .arg(format)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to run git log")?;
+
+        let stdout = child.stdout.take().context("Failed to read git log")?;
+        let mut current: Option<Commit> = None;
+        let mut stopped_early = false;
+        for line in BufReader::new(stdout).lines() {
+            let line = line.context("Failed to read git log")?;
+            if let Some(commit) = Commit::parse_header(&line) {
+                if let Some(done) = current.replace(commit) {
+                    history.finish(done, limit);
+                }
+                if pending.is_empty() && history.commits.len() >= limit {
+                    current = None;
+                    stopped_early = true;
+                    break;
+                }
+            } else if !line.is_empty()
+                && paths.contains(line.as_str())
+                && let Some(commit) = &mut current
+            {
+                pending.remove(&line);
+                commit.files.push(line);
+            }
+        }
+        if let Some(done) = current {
+            history.finish(done, limit);
+        }
+
+        if stopped_early {
+            // The rest of the log is not needed
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(history);
+        }
+        let output = child.wait_with_output().context("Failed to run git log")?;
+        if !output.status.success() {
+            bail!(
+                "git log failed in {}: {}",
+                repo.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(history)
+    }
+
+    fn finish(&mut self, commit: Commit, limit: usize) {
+        // Commits that touched no packed file are not part of the history
+        if commit.files.is_empty() {
+            return;
+        }
+        for file in &commit.files {
+            self.last_changed
+                .entry(file.clone())
+                .or_insert_with(|| commit.clone());
+        }
+        if self.commits.len() < limit {
+            self.commits.push(commit);
+        }
+    }
+
+    /// Short description of the last commit touching `relative_path`.
+    pub fn last_change(&self, relative_path: &str) -> String {
+        match self.last_changed.get(relative_path) {
+            Some(commit) => format!("{} ({}): {}", commit.hash, commit.date, commit.subject),
+            None => "the working tree".to_string(),
+        }
+    }
+
+    /// Writes the recent commits section placed after the packed files.
+    pub fn write_section<W: Write>(&self, out: &mut W) -> Result<()> {
+        writeln!(out, "\nRecent commits:")?;
+        for commit in &self.commits {
+            writeln!(
+                out,
+                "\n{} {} {}: {}",
+                commit.hash, commit.date, commit.author, commit.subject
+            )?;
+            for file in &commit.files {
+                writeln!(out, "  {}", file)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn run(repo: &Path, args: &[&str]) {
+        let status = git(repo)
+            .args(["-c", "user.name=Dev", "-c", "user.email=dev@example.com"])
+            .args(args)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_load_history_and_last_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        run(repo, &["init", "-q"]);
+        fs::write(repo.join("a.rs"), "a").unwrap();
+        fs::write(repo.join("b.rs"), "b").unwrap();
+        run(repo, &["add", "."]);
+        run(repo, &["commit", "-q", "-m", "Add a and b"]);
+        fs::write(repo.join("a.rs"), "a2").unwrap();
+        run(repo, &["commit", "-q", "-am", "Update a"]);
+        fs::write(repo.join("other.txt"), "not packed").unwrap();
+        run(repo, &["add", "other.txt"]);
+        run(repo, &["commit", "-q", "-m", "Add an unpacked file"]);
+        fs::write(repo.join("new.rs"), "n").unwrap();
+
+        let files: Vec<FileEntry> = ["a.rs", "b.rs", "new.rs"]
+            .iter()
+            .map(|name| FileEntry::fixture(name, ""))
+            .collect();
+
+        let history = GitHistory::load(repo, &files, 1).unwrap();
+
+        assert_eq!(history.commits.len(), 1);
+        assert_eq!(history.commits[0].subject, "Update a");
+        assert_eq!(history.commits[0].files, vec!["a.rs"]);
+        assert!(history.last_change("a.rs").ends_with(": Update a"));
+        assert!(history.last_change("b.rs").ends_with(": Add a and b"));
+        assert_eq!(history.last_change("new.rs"), "the working tree");
+    }
+}
@@ -0,0 +1,28 @@
+//! Packs source trees into a single LLM-friendly document and hosts the `pai code` agent.
+//!
+//! Embedders usually only need [`PackBuilder`]; the `pai` binary is a thin
+//! command-line wrapper around the same modules.
+
+pub mod archive;
+pub mod code;
+pub mod compact;
+pub mod config;
+pub mod dedupe;
+pub mod deps;
+pub mod detect;
+pub mod extract;
+pub mod filter;
+pub mod grep;
+pub mod history;
+pub mod md;
+pub mod models;
+pub mod pack;
+pub mod session;
+pub mod stats;
+pub mod template;
+pub mod tools;
+
+pub use filter::PathFilter;
+pub use md::{FileEntry, Instructions, SkipReason, SkippedEntry};
+pub use pack::{Pack, PackBuilder};
+pub use template::{PackFormat, Template};
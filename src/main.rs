@@ -1,20 +1,12 @@
-pub mod code;
-pub mod md;
-pub mod models;
-
 use clap::{Parser, Subcommand};
-use code::Agent;
-use md::{MdrsArgs, generate_markdown};
-use models::{AppError, ModelType};
+use pai::code::{Agent, AgentOptions, SessionStart};
+use pai::md::{MdrsArgs, generate_markdown};
+use pai::models::{AppError, ModelType};
+use pai::session::{SessionsArgs, run_sessions};
+use pai::stats::{StatsArgs, print_stats};
+use std::path::PathBuf;
 use std::process;
 
-// Define Result type alias specific to main, or import if generally needed
-impl From<anyhow::Error> for AppError {
-    fn from(err: anyhow::Error) -> Self {
-        AppError(format!("{:?}", err))
-    }
-}
-
 type Result<T> = std::result::Result<T, AppError>;
 
 // Top-level CLI arguments
@@ -32,6 +24,10 @@ enum Commands {
     Code(CodeArgs),
     /// Generate a Markdown file from code files
     Md(MdrsArgs),
+    /// Report what `md` would pack: sizes, token estimates and exclusions
+    Stats(StatsArgs),
+    /// List, show or delete saved `code` sessions
+    Sessions(SessionsArgs),
 }
 
 // Arguments for the `code` subcommand
@@ -40,6 +36,22 @@ struct CodeArgs {
     /// The large language model to use.
     #[arg(short, long, value_parser = clap::value_parser!(String), default_value = "claude")]
     model: String,
+
+    /// Workspace root the agent's file tools are confined to (defaults to the current directory).
+    #[arg(long)]
+    root: Option<PathBuf>,
+
+    /// Run tool calls without asking for approval, except those denied by `[code.permissions]`.
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Resume a saved session by id (or unique prefix); without an id, pick from a list.
+    #[arg(long, value_name = "ID", num_args = 0..=1)]
+    resume: Option<Option<String>>,
+
+    /// Continue the most recent session for this project.
+    #[arg(short = 'c', long = "continue", conflicts_with = "resume")]
+    continue_session: bool,
 }
 
 #[tokio::main]
@@ -63,7 +75,16 @@ pub async fn main() -> Result<()> {
             };
 
             // Use the public Agent::new function
-            match Agent::new(model_type) {
+            let options = AgentOptions {
+                root: args.root,
+                yes: args.yes,
+                session: match args.resume {
+                    Some(id) => SessionStart::Resume(id),
+                    None if args.continue_session => SessionStart::Continue,
+                    None => SessionStart::New,
+                },
+            };
+            match Agent::new(model_type, options) {
                 Ok(agent) => agent.run().await?,
                 Err(err) => {
                     eprintln!("\x1b[91mError: Failed to initialize agent: {}\x1b[0m", err);
@@ -74,11 +95,13 @@ pub async fn main() -> Result<()> {
         Commands::Md(args) => {
             println!(
                 "Generating Markdown from '{}' to '{}'...",
-                args.input_dir, args.output
+                args.scan.input_dir, args.output
             );
             generate_markdown(args)?;
             println!("Markdown generation complete.");
         }
+        Commands::Stats(args) => print_stats(args)?,
+        Commands::Sessions(args) => run_sessions(args)?,
     }
 
     Ok(())
@@ -1,19 +1,29 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::Args;
+use std::fmt;
 use std::fs::{self, File};
-use std::io::Write;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::archive::{ArchiveKind, scan_archive};
+use crate::config::{Config, TemplateConfig};
+use crate::deps::follow_dependencies;
+use crate::detect::{SNIFF_LEN, decode_text, sniff_binary};
+use crate::extract::ExtractorRegistry;
+use crate::filter::{FilterReason, PathFilter};
+use crate::grep::{GrepFilter, GrepMode};
+use crate::history::GitHistory;
+use crate::pack::PackBuilder;
+use crate::template::{PackFormat, Template};
+
+/// Options controlling which files are picked up from the input directory.
 #[derive(Args, Debug)]
-pub struct MdrsArgs {
-    /// Directory to search for code files.
+pub struct ScanArgs {
+    /// Directory (or .tar, .tar.gz or .zip archive) to search for code files.
     #[arg(short, long, default_value = ".")]
     pub input_dir: String,
 
-    /// Output Markdown file path.
-    #[arg(short, long, default_value = "llm.md")]
-    pub output: String,
-
     /// Comma-separated list of file extensions to include (e.g., "rs,toml").
     #[arg(short, long)]
     pub extensions: Option<String>,
@@ -21,173 +31,722 @@ pub struct MdrsArgs {
     /// Comma-separated list of patterns (filenames or extensions like ".lock") to ignore.
     #[arg(short = 'n', long)]
     pub ignore: Option<String>,
+
+    /// Pack raw file contents instead of running the notebook, CSV and lockfile extractors.
+    #[arg(long)]
+    pub no_extract: bool,
+
+    /// Keep text outputs of notebook code cells (images are always dropped).
+    #[arg(long)]
+    pub notebook_outputs: bool,
+
+    /// Number of data rows kept from CSV/TSV files.
+    #[arg(long, default_value_t = 10)]
+    pub csv_rows: usize,
+
+    /// Pack files that look binary (by signature or control characters) if they decode as text.
+    #[arg(long)]
+    pub include_binary: bool,
+
+    /// Pack minified files, source maps and other files with very long lines.
+    #[arg(long)]
+    pub include_minified: bool,
+
+    /// Pack files marked `@generated` or `DO NOT EDIT` and protobuf output like `.pb.rs`.
+    #[arg(long)]
+    pub include_generated: bool,
 }
 
-pub fn is_binary_file(path: &str) -> Result<bool> {
-    let content = fs::read(path)?;
+impl ScanArgs {
+    /// Builder scanning the input directory with these options and the `[filter]` config.
+    pub fn pack_builder(&self, config: &Config) -> PackBuilder {
+        let extractors = if self.no_extract {
+            ExtractorRegistry::new()
+        } else {
+            ExtractorRegistry::with_defaults(self.notebook_outputs, self.csv_rows)
+        };
 
-    // Check for null bytes
-    if content.contains(&0) {
-        return Ok(true);
+        PackBuilder::new()
+            .root(&self.input_dir)
+            // Parse extensions if provided
+            .extensions(split_list(self.extensions.as_deref()))
+            // Parse ignore patterns if provided
+            .filter(
+                PathFilter::from_config(&config.filter)
+                    .with_ignore(&split_list(self.ignore.as_deref())),
+            )
+            .extractors(extractors)
+            .include_binary(self.include_binary)
+            .include_minified(self.include_minified)
+            .include_generated(self.include_generated)
     }
+}
+
+#[derive(Args, Debug)]
+pub struct MdrsArgs {
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    /// Output Markdown file path.
+    #[arg(short, long, default_value = "llm.md")]
+    pub output: String,
 
-    // Check for high percentage of non-printable characters
-    let non_printable_count = content
-        .iter()
-        .filter(|&&c| c < 32 && c != 9 && c != 10 && c != 13) // 9=tab, 10=LF, 13=CR
-        .count();
+    /// Built-in output format (overrides the format set in `.pai.toml`).
+    #[arg(short, long, value_enum)]
+    pub format: Option<PackFormat>,
 
-    let total_bytes = content.len();
-    let non_printable_ratio = non_printable_count as f64 / total_bytes as f64;
+    /// Template file used to render the pack (overrides `--format` and `.pai.toml`).
+    #[arg(short, long)]
+    pub template: Option<String>,
 
-    // If more than 10% of bytes are non-printable, consider it binary
-    Ok(non_printable_ratio > 0.1)
-}
+    /// Instructions placed before the file contents: literal text, `@file`, or `-` for stdin.
+    #[arg(short, long)]
+    pub prompt: Option<String>,
 
-pub fn should_skip_path(path: &str, ignore_patterns: &[String]) -> bool {
-    let components: Vec<&str> = path.split('/').collect();
-    println!("Checking path: {}", path);
+    /// Instructions placed after the file contents: literal text, `@file`, or `-` for stdin.
+    #[arg(short, long)]
+    pub suffix: Option<String>,
+
+    /// Repeat the prompt at the end of the pack, which helps with long contexts.
+    #[arg(long)]
+    pub repeat_prompt: bool,
+
+    /// Emit byte-identical files once and reference the copy from the other paths.
+    #[arg(long)]
+    pub dedupe: bool,
+
+    /// Report pairs of files whose lines overlap at least this much (0.0 to 1.0).
+    #[arg(long, value_name = "THRESHOLD", value_parser = parse_threshold)]
+    pub near_duplicates: Option<f64>,
+
+    /// Only include files whose contents match this regex (repeatable).
+    #[arg(short, long, value_name = "REGEX")]
+    pub grep: Vec<String>,
+
+    /// Whether a file must match any or all of the --grep patterns.
+    #[arg(long, value_enum, default_value_t = GrepMode::Any)]
+    pub grep_mode: GrepMode,
+
+    /// Include only the matching lines plus this many lines of context instead of whole files.
+    #[arg(long, value_name = "LINES", requires = "grep")]
+    pub grep_context: Option<usize>,
+
+    /// Start from this entry file and only include the local modules it transitively imports (repeatable).
+    #[arg(long, value_name = "FILE")]
+    pub follow_deps: Vec<String>,
+
+    /// Maximum number of import levels followed from the --follow-deps entries.
+    #[arg(long, default_value_t = 10, requires = "follow_deps")]
+    pub deps_depth: usize,
+
+    /// Append the last N commits touching the packed files and note each file's last change.
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    pub git_history: Option<usize>,
+
+    /// Stop adding files once their estimated tokens would exceed this budget.
+    #[arg(long, value_name = "TOKENS")]
+    pub budget: Option<usize>,
+}
 
-    // Skip the first component if it's "." or ".."
-    let components_to_check = if components[0] == "." || components[0] == ".." {
-        &components[1..]
+fn parse_threshold(value: &str) -> std::result::Result<f64, String> {
+    let threshold: f64 = value
+        .parse()
+        .map_err(|_| format!("'{}' is not a number", value))?;
+    if (0.0..=1.0).contains(&threshold) {
+        Ok(threshold)
     } else {
-        &components[..]
-    };
-
-    // Check if the full filename matches any ignore pattern
-    if let Some(filename) = components_to_check.last() {
-        if ignore_patterns.iter().any(|pattern| filename == pattern) {
-            println!("Skipping ignored file: {}", filename);
-            return true;
+        Err("threshold must be between 0.0 and 1.0".to_string())
+    }
+}
+
+/// Why a file or directory was left out of a scan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    OutputFile,
+    Filtered(FilterReason),
+    Extension(String),
+    Binary(String),
+    Minified(usize),
+    Generated(String),
+    NotUtf8,
+    Unreadable(String),
+    /// An archive entry larger than the per-entry limit, in bytes.
+    TooLarge(u64),
+    NoGrepMatch,
+    NotADependency,
+    OverBudget(usize),
+}
+
+impl SkipReason {
+    /// Short, stable identifier of the rule that produced this reason.
+    pub fn rule(&self) -> &'static str {
+        match self {
+            SkipReason::OutputFile => "output-file",
+            SkipReason::Filtered(reason) => reason.rule(),
+            SkipReason::Extension(_) => "extension-filter",
+            SkipReason::Binary(_) => "binary",
+            SkipReason::Minified(_) => "minified",
+            SkipReason::Generated(_) => "generated",
+            SkipReason::NotUtf8 => "not-utf8",
+            SkipReason::Unreadable(_) => "unreadable",
+            SkipReason::TooLarge(_) => "too-large",
+            SkipReason::NoGrepMatch => "grep",
+            SkipReason::NotADependency => "follow-deps",
+            SkipReason::OverBudget(_) => "budget",
         }
     }
+}
 
-    for component in components_to_check {
-        if component.starts_with(".") {
-            println!("Skipping hidden component: {}", component);
-            return true;
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::OutputFile => write!(f, "output file"),
+            SkipReason::Filtered(reason) => write!(f, "{}", reason),
+            SkipReason::Extension(ext) => write!(f, "extension '{}' not selected", ext),
+            SkipReason::Binary(kind) => write!(f, "binary file ({})", kind),
+            SkipReason::Minified(longest) => {
+                write!(f, "looks minified (longest line {} bytes)", longest)
+            }
+            SkipReason::Generated(marker) => write!(f, "generated code ('{}')", marker),
+            SkipReason::NotUtf8 => write!(f, "not valid UTF-8 or UTF-16 text"),
+            SkipReason::Unreadable(err) => write!(f, "unreadable: {}", err),
+            SkipReason::TooLarge(bytes) => write!(f, "archive entry over {} bytes", bytes),
+            SkipReason::NoGrepMatch => write!(f, "no --grep match"),
+            SkipReason::NotADependency => write!(f, "not reachable from --follow-deps entries"),
+            SkipReason::OverBudget(tokens) => {
+                write!(f, "~{} tokens would exceed the token budget", tokens)
+            }
         }
+    }
+}
 
-        if [
-            "target",
-            "build",
-            "dist",
-            "node_modules",
-            ".git",
-            ".venv",
-            "venv",
-            "__pycache__",
-            ".pytest_cache",
-            ".idea",
-            ".vscode",
-            ".next",
-            ".nuxt",
-            ".docusaurus",
-            ".cargo",
-            ".rustup",
-        ]
-        .contains(&component)
-        {
-            println!("Skipping build directory: {}", component);
-            return true;
+/// A text file selected for packing.
+#[derive(Debug, Clone)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    pub relative_path: String,
+    pub content: String,
+}
+
+impl FileEntry {
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.content)
+    }
+
+    pub fn language(&self) -> &'static str {
+        language_for(&self.path)
+    }
+
+    /// Test fixture whose path and relative path are both `path`.
+    #[cfg(test)]
+    pub(crate) fn fixture(path: &str, content: &str) -> Self {
+        FileEntry {
+            path: PathBuf::from(path),
+            relative_path: path.to_string(),
+            content: content.to_string(),
         }
     }
+}
 
-    false
+/// A file or directory excluded from a scan, with the rule that excluded it.
+#[derive(Debug, Clone)]
+pub struct SkippedEntry {
+    pub relative_path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub reason: SkipReason,
 }
 
-pub fn generate_markdown(args: MdrsArgs) -> Result<()> {
-    // Parse extensions if provided
-    let extensions: Vec<String> = args
-        .extensions
-        .as_deref()
-        .map(|exts| exts.split(',').map(String::from).collect())
-        .unwrap_or_default();
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub files: Vec<FileEntry>,
+    pub skipped: Vec<SkippedEntry>,
+}
 
-    // Parse ignore patterns if provided
-    let ignore_patterns: Vec<String> = args
-        .ignore
-        .as_deref()
-        .map(|patterns| patterns.split(',').map(String::from).collect())
+/// Rough token estimate used for reporting (about four bytes per token).
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Human-readable language name derived from the file extension.
+pub fn language_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
         .unwrap_or_default();
 
-    // Create output file
-    let mut output_file = File::create(&args.output)
-        .with_context(|| format!("Failed to create output file: {}", args.output))?;
+    match ext.as_str() {
+        "rs" => "Rust",
+        "py" | "pyi" => "Python",
+        "js" | "mjs" | "cjs" | "jsx" => "JavaScript",
+        "ts" | "tsx" | "mts" | "cts" => "TypeScript",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "C++",
+        "cs" => "C#",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "swift" => "Swift",
+        "sh" | "bash" | "zsh" => "Shell",
+        "html" | "htm" => "HTML",
+        "css" | "scss" | "sass" => "CSS",
+        "json" => "JSON",
+        "toml" => "TOML",
+        "yaml" | "yml" => "YAML",
+        "xml" => "XML",
+        "md" | "markdown" => "Markdown",
+        "sql" => "SQL",
+        "ipynb" => "Jupyter",
+        "txt" => "Text",
+        _ => "Other",
+    }
+}
 
-    let mut file_count = 0;
-    // Walk through all files in the directory
-    for entry in WalkDir::new(&args.input_dir)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
+/// Sniffs the first few KB of `path` for binary signatures and control characters.
+pub fn is_binary_file(path: &str) -> Result<bool> {
+    let mut prefix = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut prefix)?;
+    Ok(is_binary_content(&prefix))
+}
+
+pub fn is_binary_content(content: &[u8]) -> bool {
+    sniff_binary(content).is_some()
+}
+
+fn split_list(list: Option<&str>) -> Vec<String> {
+    list.map(|items| items.split(',').map(String::from).collect())
+        .unwrap_or_default()
+}
+
+/// Path and extension rules shared by directory and archive scans.
+pub(crate) struct ScanRules {
+    extensions: Vec<String>,
+    filter: PathFilter,
+    include_binary: bool,
+}
+
+impl ScanRules {
+    pub(crate) fn new(extensions: Vec<String>, filter: PathFilter, include_binary: bool) -> Self {
+        ScanRules {
+            extensions,
+            filter,
+            include_binary,
+        }
+    }
+
+    /// Rule excluding a whole directory, if any. Paths are relative to the scan root.
+    pub(crate) fn dir_reason(&self, relative_path: &Path) -> Option<SkipReason> {
+        self.filter.explain(relative_path).map(SkipReason::Filtered)
+    }
+
+    /// Rule excluding a file based on its path alone, if any.
+    pub(crate) fn file_reason(&self, relative_path: &Path) -> Option<SkipReason> {
+        if let Some(reason) = self.filter.explain(relative_path) {
+            return Some(SkipReason::Filtered(reason));
+        }
+
+        // Check extension if specified
+        if !self.extensions.is_empty()
+            && let Some(ext) = relative_path.extension()
+        {
+            let ext_str = ext.to_string_lossy().to_string();
+            if !self.extensions.contains(&ext_str) {
+                return Some(SkipReason::Extension(ext_str));
+            }
+        }
+
+        None
+    }
+}
+
+impl ScanRules {
+    /// Rule excluding a file based on its first [`SNIFF_LEN`] bytes, if any.
+    pub(crate) fn sniff_reason(&self, prefix: &[u8]) -> Option<SkipReason> {
+        if self.include_binary {
+            return None;
+        }
+        sniff_binary(prefix).map(|kind| SkipReason::Binary(kind.to_string()))
+    }
+
+    /// Turns raw file bytes into packable text, rejecting binary and undecodable content.
+    pub(crate) fn decode(&self, bytes: Vec<u8>) -> std::result::Result<String, SkipReason> {
+        if let Some(reason) = self.sniff_reason(&bytes) {
+            return Err(reason);
+        }
+        decode_text(bytes).ok_or(SkipReason::NotUtf8)
+    }
+}
+
+/// Reads a file, checking its leading bytes before loading the rest.
+fn read_file(path: &Path, rules: &ScanRules) -> std::result::Result<String, SkipReason> {
+    let unreadable = |err: io::Error| SkipReason::Unreadable(err.to_string());
+    let mut file = File::open(path).map_err(unreadable)?;
+
+    let mut bytes = Vec::with_capacity(SNIFF_LEN);
+    (&mut file)
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut bytes)
+        .map_err(unreadable)?;
+    if let Some(reason) = rules.sniff_reason(&bytes) {
+        return Err(reason);
+    }
+
+    file.read_to_end(&mut bytes).map_err(unreadable)?;
+    decode_text(bytes).ok_or(SkipReason::NotUtf8)
+}
+
+impl Scan {
+    pub(crate) fn skip(
+        &mut self,
+        relative_path: String,
+        is_dir: bool,
+        size: u64,
+        reason: SkipReason,
+    ) {
+        self.skipped.push(SkippedEntry {
+            relative_path,
+            is_dir,
+            size,
+            reason,
+        });
+    }
+}
+
+/// Walks `root` and sorts every file into packed or skipped.
+///
+/// `root` may also be a `.tar`, `.tar.gz` or `.zip` archive, which is read
+/// in place as if it were a directory. Skipped directories are pruned and
+/// reported once rather than per file. `output` names the pack being written
+/// so it is never packed into itself.
+pub(crate) fn scan_root(root: &Path, rules: &ScanRules, output: Option<&Path>) -> Result<Scan> {
+    if root.is_file()
+        && let Some(kind) = ArchiveKind::from_path(root)
     {
+        return scan_archive(root, kind, rules);
+    }
+
+    // Compare canonical paths so "./llm.md" and "llm.md" are recognised as the same file
+    let output = output.map(|output| fs::canonicalize(output).unwrap_or_else(|_| output.into()));
+
+    let mut scan = Scan::default();
+    let mut walker = WalkDir::new(root).sort_by_file_name().into_iter();
+
+    while let Some(entry) = walker.next() {
+        let Ok(entry) = entry else { continue };
         let path = entry.path();
-        let path_str = path.to_string_lossy();
 
-        println!("Processing file: {}", path_str);
+        // Rules apply to the path relative to the scan root
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let relative_path = relative.to_string_lossy().to_string();
 
-        // Skip the output file itself
-        if path_str == args.output {
-            println!("Skipping output file");
+        if entry.file_type().is_dir() {
+            if entry.depth() > 0
+                && let Some(reason) = rules.dir_reason(relative)
+            {
+                walker.skip_current_dir();
+                scan.skip(relative_path, true, 0, reason);
+            }
             continue;
         }
 
-        // Check if path should be skipped
-        if should_skip_path(&path_str, &ignore_patterns) {
-            println!("Skipping file due to path rules");
+        if !entry.file_type().is_file() {
             continue;
         }
 
-        // Check if file is binary
-        if let Ok(true) = is_binary_file(&path_str) {
-            println!("Skipping binary file: {}", path_str);
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+
+        // Skip the output file itself
+        if output
+            .as_deref()
+            .is_some_and(|output| fs::canonicalize(path).is_ok_and(|path| path == output))
+        {
+            scan.skip(relative_path, false, size, SkipReason::OutputFile);
             continue;
         }
 
-        // Check extension if specified
-        if !extensions.is_empty() {
-            if let Some(ext) = path.extension() {
-                let ext_str = ext.to_string_lossy().to_string();
-                if !extensions.contains(&ext_str) {
-                    println!("Skipping file due to extension: {}", ext_str);
-                    continue;
-                }
-            }
+        if let Some(reason) = rules.file_reason(relative) {
+            scan.skip(relative_path, false, size, reason);
+            continue;
         }
 
-        // Check if file extension is in ignore patterns
-        if let Some(ext) = path.extension() {
-            let ext_str = format!(".{}", ext.to_string_lossy());
-            if ignore_patterns.contains(&ext_str) {
-                println!("Skipping file with ignored extension: {}", ext_str);
-                continue;
-            }
+        // Read file content
+        match read_file(path, rules) {
+            Ok(content) => scan.files.push(FileEntry {
+                path: path.to_path_buf(),
+                relative_path,
+                content,
+            }),
+            Err(reason) => scan.skip(relative_path, false, size, reason),
         }
+    }
 
-        // Get relative path
-        let relative_path = path
-            .strip_prefix(&args.input_dir)
-            .unwrap_or(path)
-            .to_string_lossy()
-            .to_string();
+    Ok(scan)
+}
 
-        // Read file content
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+fn load_template_file(path: &str) -> Result<Template> {
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read template file: {}", path))?;
+    Template::parse(&source).with_context(|| format!("Invalid template file: {}", path))
+}
+
+/// Picks the pack template: `--template`, then `--format`, then `.pai.toml`, then markdown.
+pub fn resolve_template(args: &MdrsArgs, config: &Config) -> Result<Template> {
+    if let Some(path) = &args.template {
+        return load_template_file(path);
+    }
+    if let Some(format) = args.format {
+        return Ok(Template::builtin(format));
+    }
+
+    match &config.md.template {
+        Some(TemplateConfig::File(path)) => load_template_file(path),
+        Some(TemplateConfig::Sections {
+            header,
+            file,
+            footer,
+        }) => {
+            Template::from_sections(header, file, footer).context("Invalid template in .pai.toml")
+        }
+        None => Ok(Template::builtin(
+            config.md.format.unwrap_or(PackFormat::Markdown),
+        )),
+    }
+}
+
+/// Instructions wrapped around the rendered files.
+#[derive(Debug, Default)]
+pub struct Instructions {
+    pub prompt: Option<String>,
+    pub suffix: Option<String>,
+    pub repeat_prompt: bool,
+}
+
+/// Resolves an instruction argument: `@path` reads a file, `-` reads stdin, anything else is literal.
+pub fn read_instruction(value: &str) -> Result<String> {
+    if value == "-" {
+        let mut text = String::new();
+        io::stdin()
+            .read_to_string(&mut text)
+            .context("Failed to read instructions from stdin")?;
+        Ok(text)
+    } else if let Some(path) = value.strip_prefix('@') {
+        fs::read_to_string(path)
+            .with_context(|| format!("Failed to read instructions file: {}", path))
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Collects prompt and suffix from the CLI, falling back to `.pai.toml`.
+pub fn resolve_instructions(args: &MdrsArgs, config: &Config) -> Result<Instructions> {
+    if args.prompt.as_deref() == Some("-") && args.suffix.as_deref() == Some("-") {
+        bail!("Only one of --prompt and --suffix can be read from stdin");
+    }
+
+    let prompt = args.prompt.as_ref().or(config.md.prompt.as_ref());
+    let suffix = args.suffix.as_ref().or(config.md.suffix.as_ref());
+
+    Ok(Instructions {
+        prompt: prompt.map(|p| read_instruction(p)).transpose()?,
+        suffix: suffix.map(|s| read_instruction(s)).transpose()?,
+        repeat_prompt: args.repeat_prompt || config.md.repeat_prompt,
+    })
+}
+
+/// Writes the full pack: prompt, rendered files, git history, suffix and the
+/// optional repeated prompt.
+pub fn write_pack<W: Write>(
+    out: &mut W,
+    template: &Template,
+    files: &[FileEntry],
+    instructions: &Instructions,
+    history: Option<&GitHistory>,
+) -> Result<()> {
+    if let Some(prompt) = &instructions.prompt {
+        writeln!(out, "{}\n", prompt.trim_end())?;
+    }
 
-        // Write to markdown file
-        writeln!(output_file, "{}", relative_path)?;
-        writeln!(output_file, "```")?;
-        writeln!(output_file, "{}", content)?;
-        writeln!(output_file, "```\n")?;
+    template.render(files, history, out)?;
 
-        file_count += 1;
-        println!("Successfully processed file: {}", relative_path);
+    if let Some(history) = history {
+        history.write_section(out)?;
+    }
+
+    if let Some(suffix) = &instructions.suffix {
+        writeln!(out, "\n{}", suffix.trim_end())?;
+    }
+
+    if instructions.repeat_prompt
+        && let Some(prompt) = &instructions.prompt
+    {
+        writeln!(out, "\n{}", prompt.trim_end())?;
+    }
+
+    Ok(())
+}
+
+/// Keeps only the files reachable from `entries`, ordered dependencies first.
+pub fn apply_follow_deps(
+    scan: &mut Scan,
+    entries: &[String],
+    input_dir: &str,
+    max_depth: usize,
+) -> Result<()> {
+    let order = follow_dependencies(&scan.files, entries, input_dir, max_depth)?;
+    let mut remaining = std::mem::take(&mut scan.files);
+
+    for path in order {
+        if let Some(index) = remaining.iter().position(|file| {
+            Path::new(&file.relative_path)
+                .components()
+                .eq(path.components())
+        }) {
+            scan.files.push(remaining.swap_remove(index));
+        }
+    }
+
+    scan.skipped
+        .extend(remaining.into_iter().map(|file| SkippedEntry {
+            size: file.content.len() as u64,
+            relative_path: file.relative_path,
+            is_dir: false,
+            reason: SkipReason::NotADependency,
+        }));
+    Ok(())
+}
+
+/// Drops files that do not match `grep` and trims the rest to the selected regions.
+pub fn apply_grep(scan: &mut Scan, grep: &GrepFilter) {
+    let (matched, unmatched): (Vec<_>, Vec<_>) = std::mem::take(&mut scan.files)
+        .into_iter()
+        .partition(|file| grep.matches(&file.content));
+
+    scan.skipped
+        .extend(unmatched.into_iter().map(|file| SkippedEntry {
+            size: file.content.len() as u64,
+            relative_path: file.relative_path,
+            is_dir: false,
+            reason: SkipReason::NoGrepMatch,
+        }));
+
+    scan.files = matched
+        .into_iter()
+        .map(|file| FileEntry {
+            content: grep.select(&file.content),
+            ..file
+        })
+        .collect();
+}
+
+/// Builder for everything `pai md` would pack, without the output sink.
+pub fn md_pack_builder(args: &MdrsArgs, config: &Config) -> Result<PackBuilder> {
+    let mut builder = args
+        .scan
+        .pack_builder(config)
+        .template(resolve_template(args, config)?)
+        .instructions(resolve_instructions(args, config)?)
+        .output(&args.output)
+        .dedupe(args.dedupe);
+
+    if !args.follow_deps.is_empty() {
+        builder = builder.follow_deps(args.follow_deps.clone(), args.deps_depth);
+    }
+    if !args.grep.is_empty() {
+        builder = builder.grep(GrepFilter::new(
+            &args.grep,
+            args.grep_mode,
+            args.grep_context,
+        )?);
+    }
+    if let Some(threshold) = args.near_duplicates {
+        builder = builder.near_duplicates(threshold);
+    }
+    if let Some(budget) = args.budget {
+        builder = builder.budget(budget);
+    }
+    if let Some(limit) = args.git_history {
+        builder = builder.git_history(limit);
+    }
+    Ok(builder)
+}
+
+pub fn generate_markdown(args: MdrsArgs) -> Result<()> {
+    let config = Config::load(Path::new("."))?;
+    let builder = md_pack_builder(&args, &config)?;
+
+    // Create output file
+    let output_file = File::create(&args.output)
+        .with_context(|| format!("Failed to create output file: {}", args.output))?;
+
+    let pack = builder.sink(output_file).build()?;
+
+    for skipped in &pack.skipped {
+        println!("Skipping {}: {}", skipped.relative_path, skipped.reason);
+    }
+
+    for duplicate in &pack.duplicates {
+        println!(
+            "Collapsing duplicate: {} (identical to {})",
+            duplicate.path, duplicate.original
+        );
+    }
+
+    for near in &pack.near_duplicates {
+        println!(
+            "Near-duplicate: {} ~ {} ({:.0}% similar)",
+            near.first,
+            near.second,
+            near.similarity * 100.0
+        );
+    }
+
+    for file in &pack.files {
+        println!("Processing file: {}", file.relative_path);
     }
 
     println!("Successfully created markdown file at: {}", args.output);
-    println!("Total files processed: {}", file_count);
+    println!("Total files processed: {}", pack.files.len());
+    if !pack.duplicates.is_empty() {
+        println!(
+            "Collapsed {} duplicate files, saving ~{} tokens",
+            pack.duplicates.len(),
+            pack.tokens_saved()
+        );
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::PackFormat;
+
+    #[test]
+    fn test_write_pack_places_instructions() {
+        let files = vec![FileEntry::fixture("a.txt", "A")];
+        let instructions = Instructions {
+            prompt: Some("Explain this.\n".to_string()),
+            suffix: Some("Answer briefly.".to_string()),
+            repeat_prompt: true,
+        };
+
+        let mut out = Vec::new();
+        write_pack(
+            &mut out,
+            &Template::builtin(PackFormat::Markdown),
+            &files,
+            &instructions,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Explain this.\n\na.txt\n```\nA\n```\n\n\nAnswer briefly.\n\nExplain this.\n"
+        );
+    }
+}
@@ -124,6 +124,10 @@ impl Model for ClaudeModel {
     fn name(&self) -> &'static str {
         "Claude"
     }
+
+    fn context_window(&self) -> usize {
+        200_000
+    }
 }
 
 // Helper function to create a default Claude model instance
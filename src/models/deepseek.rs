@@ -73,9 +73,7 @@ struct DeepSeekResponse {
 
 #[derive(Deserialize, Debug)]
 struct DeepSeekChoice {
-    index: u32,
     message: DeepSeekMessage,
-    finish_reason: String,
 }
 
 // --- DeepSeek Model Implementation ---
@@ -122,10 +120,14 @@ impl DeepSeekModel {
                     "type": tool.input_schema.schema_type,
                     "properties": tool.input_schema.properties.iter()
                         .map(|(name, prop)| {
-                            (name.clone(), json!({
+                            let mut property = json!({
                                 "type": prop.property_type,
                                 "description": prop.description
-                            }))
+                            });
+                            if let Some(items) = &prop.items {
+                                property["items"] = items.clone();
+                            }
+                            (name.clone(), property)
                         })
                         .collect::<HashMap<String, Value>>(),
                     "required": tool.input_schema.required
@@ -253,7 +255,7 @@ impl DeepSeekModel {
                         match block {
                             ContentBlock::Text { text } => {
                                 if !text_content.is_empty() {
-                                    text_content.push_str("\n");
+                                    text_content.push('\n');
                                 }
                                 text_content.push_str(text);
                             }
@@ -378,8 +380,8 @@ impl Model for DeepSeekModel {
         }
 
         // Handle tools if supported and provided
-        let deepseek_tools = if self.supports_tools() && tools.is_some() {
-            let tool_defs = Self::convert_to_deepseek_tools(tools.unwrap());
+        let deepseek_tools = if let Some(tools) = tools.filter(|_| self.supports_tools()) {
+            let tool_defs = Self::convert_to_deepseek_tools(tools);
             if !tool_defs.is_empty() {
                 Some(tool_defs)
             } else {
@@ -447,6 +449,10 @@ impl Model for DeepSeekModel {
     fn name(&self) -> &'static str {
         "DeepSeek"
     }
+
+    fn context_window(&self) -> usize {
+        64_000
+    }
 }
 
 // Helper function to create a default DeepSeek model instance
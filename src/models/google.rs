@@ -83,6 +83,8 @@ struct GoogleParameterProperty {
     #[serde(rename = "type")]
     property_type: String, // "STRING", "NUMBER", etc.
     description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    items: Option<Value>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -131,40 +133,49 @@ impl GoogleModel {
 
     // --- Conversion Logic ---
 
+    /// Uppercases the `type` fields of a nested JSON schema, as Google expects
+    fn convert_schema_types(schema: &Value) -> Value {
+        match schema {
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| match (key.as_str(), value) {
+                        ("type", Value::String(t)) => (key.clone(), json!(t.to_uppercase())),
+                        _ => (key.clone(), Self::convert_schema_types(value)),
+                    })
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
     /// Converts our common Tool format to Google's FunctionDeclaration format
     fn convert_to_google_functions(tools: &[Tool]) -> Vec<GoogleFunctionDeclaration> {
         tools
             .iter()
             .map(|tool| {
                 // Convert properties to Google format
-                let properties = tool
-                    .input_schema
-                    .properties
-                    .iter()
-                    .map(|(name, prop)| {
-                        // Convert property type to Google's uppercase format
-                        let google_type = match prop.property_type.to_uppercase().as_str() {
-                            "STRING" | "NUMBER" | "BOOLEAN" | "ARRAY" | "OBJECT" => {
-                                prop.property_type.to_uppercase()
-                            }
-                            // Default to STRING for simple types
-                            "string" => "STRING".to_string(),
-                            "integer" | "number" => "NUMBER".to_string(),
-                            "boolean" => "BOOLEAN".to_string(),
-                            "array" => "ARRAY".to_string(),
-                            "object" => "OBJECT".to_string(),
-                            _ => "STRING".to_string(), // Default fallback
-                        };
-
-                        (
-                            name.clone(),
-                            GoogleParameterProperty {
-                                property_type: google_type,
-                                description: prop.description.clone(),
-                            },
-                        )
-                    })
-                    .collect();
+                let properties =
+                    tool.input_schema
+                        .properties
+                        .iter()
+                        .map(|(name, prop)| {
+                            // Convert property type to Google's uppercase format
+                            let google_type = match prop.property_type.to_uppercase().as_str() {
+                                "STRING" | "INTEGER" | "NUMBER" | "BOOLEAN" | "ARRAY"
+                                | "OBJECT" => prop.property_type.to_uppercase(),
+                                _ => "STRING".to_string(), // Default fallback
+                            };
+
+                            (
+                                name.clone(),
+                                GoogleParameterProperty {
+                                    property_type: google_type,
+                                    description: prop.description.clone(),
+                                    items: prop.items.as_ref().map(Self::convert_schema_types),
+                                },
+                            )
+                        })
+                        .collect();
 
                 // Convert required fields if present
                 let required = tool.input_schema.required.clone();
@@ -197,19 +208,17 @@ impl GoogleModel {
                 let parts: Vec<GooglePart> = msg
                     .content
                     .iter()
-                    .filter_map(|block| {
+                    .map(|block| {
                         match block {
-                            ContentBlock::Text { text } => {
-                                Some(GooglePart::Text { text: text.clone() })
-                            }
+                            ContentBlock::Text { text } => GooglePart::Text { text: text.clone() },
                             ContentBlock::ToolUse { name, input, .. } => {
                                 // Convert to Google's function_call format
-                                Some(GooglePart::FunctionCall {
+                                GooglePart::FunctionCall {
                                     function_call: GoogleFunctionCall {
                                         name: name.clone(),
                                         args: input.clone(),
                                     },
-                                })
+                                }
                             }
                             ContentBlock::ToolResult {
                                 tool_use_id: _,
@@ -229,12 +238,12 @@ impl GoogleModel {
 
                                 // In a real implementation, we would need to look up the function name
                                 // from the previous tool_use_id, but here we'll use a placeholder
-                                Some(GooglePart::FunctionResponse {
+                                GooglePart::FunctionResponse {
                                     function_response: GoogleFunctionResponse {
                                         name: "unknown_function".to_string(), // Placeholder
                                         response: response_value,
                                     },
-                                })
+                                }
                             }
                         }
                     })
@@ -300,8 +309,8 @@ impl Model for GoogleModel {
         system_prompt: Option<&str>,
     ) -> Result<ModelResponse, AppError> {
         // Handle tools if supported and provided
-        let google_tools = if self.supports_tools() && tools.is_some() {
-            let function_declarations = Self::convert_to_google_functions(tools.unwrap());
+        let google_tools = if let Some(tools) = tools.filter(|_| self.supports_tools()) {
+            let function_declarations = Self::convert_to_google_functions(tools);
             if !function_declarations.is_empty() {
                 Some(vec![GoogleTool {
                     function_declarations,
@@ -385,6 +394,14 @@ impl Model for GoogleModel {
     fn name(&self) -> &'static str {
         "Google"
     }
+
+    fn context_window(&self) -> usize {
+        if self.model_name.starts_with("gemini-1.0") {
+            32_768
+        } else {
+            1_048_576
+        }
+    }
 }
 
 // Helper function to create a default Google model instance
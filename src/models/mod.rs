@@ -62,6 +62,9 @@ pub struct ToolSchemaProperty {
     #[serde(rename = "type")]
     pub property_type: String,
     pub description: String,
+    /// JSON schema of the elements when `property_type` is `"array"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<Value>,
 }
 
 #[derive(Debug)]
@@ -75,6 +78,12 @@ impl std::fmt::Display for AppError {
 
 impl std::error::Error for AppError {}
 
+impl From<anyhow::Error> for AppError {
+    fn from(err: anyhow::Error) -> Self {
+        AppError(format!("{:?}", err))
+    }
+}
+
 // --- Model Trait ---
 
 #[async_trait]
@@ -92,6 +101,9 @@ pub trait Model: Send + Sync {
 
     /// Gets the name of the model implementation.
     fn name(&self) -> &'static str;
+
+    /// Tokens the model accepts per request, prompt and tools included.
+    fn context_window(&self) -> usize;
 }
 
 // Enum to select model type
@@ -1,4 +1,4 @@
-use super::{AppError, ContentBlock, Message, Model, ModelResponse, Tool, ToolSchema};
+use super::{AppError, ContentBlock, Message, Model, ModelResponse, Tool};
 use async_trait::async_trait;
 use reqwest::{Client, header};
 use serde::{Deserialize, Serialize};
@@ -53,12 +53,10 @@ struct OpenAIFunction {
 }
 
 // Response structures
+// Only the fields read are declared; serde skips the rest (object, model, ...).
 #[derive(Deserialize, Debug)]
 struct OpenAIChatCompletionResponse {
     id: String,
-    object: String,
-    created: u64,
-    model: String,
     choices: Vec<OpenAIChoice>,
     // usage: Option<OpenAIUsage>, // Add usage if needed
     // system_fingerprint: Option<String>,
@@ -66,10 +64,7 @@ struct OpenAIChatCompletionResponse {
 
 #[derive(Deserialize, Debug)]
 struct OpenAIChoice {
-    index: u32,
     message: OpenAIMessage,
-    // logprobs: Option<Value>, // Add logprobs if needed
-    finish_reason: String, // e.g., "stop", "length", "tool_calls"
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -124,10 +119,14 @@ impl OpenAIModel {
                     "type": tool.input_schema.schema_type, // Should be "object"
                     "properties": tool.input_schema.properties.iter()
                         .map(|(name, prop)| {
-                            (name.clone(), json!({
+                            let mut property = json!({
                                 "type": prop.property_type,
                                 "description": prop.description
-                            }))
+                            });
+                            if let Some(items) = &prop.items {
+                                property["items"] = items.clone();
+                            }
+                            (name.clone(), property)
                         })
                         .collect::<HashMap<String, Value>>(),
                     "required": tool.input_schema.required
@@ -282,7 +281,7 @@ impl OpenAIModel {
         // Filter out any messages with empty content and no tool calls/results
         // to avoid sending useless messages
         openai_messages.retain(|msg| {
-            let has_content = msg.content.as_ref().map_or(false, |c| !c.is_empty());
+            let has_content = msg.content.as_ref().is_some_and(|c| !c.is_empty());
             let has_tool_calls = msg.tool_calls.is_some();
             let has_tool_call_id = msg.tool_call_id.is_some();
 
@@ -307,10 +306,10 @@ impl OpenAIModel {
         let message = first_choice.message;
 
         // Add text content only if present and not empty
-        if let Some(text_content) = message.content {
-            if !text_content.is_empty() {
-                content_blocks.push(ContentBlock::Text { text: text_content });
-            }
+        if let Some(text_content) = message.content
+            && !text_content.is_empty()
+        {
+            content_blocks.push(ContentBlock::Text { text: text_content });
         }
 
         // Add tool calls if present
@@ -430,6 +429,16 @@ impl Model for OpenAIModel {
     fn name(&self) -> &'static str {
         "OpenAI"
     }
+
+    fn context_window(&self) -> usize {
+        if self.model_name.starts_with("gpt-4.1") {
+            1_047_576
+        } else if self.model_name.starts_with('o') {
+            200_000
+        } else {
+            128_000
+        }
+    }
 }
 
 // TODO: Implement default_openai() helper function
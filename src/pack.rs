@@ -0,0 +1,528 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+
+use crate::dedupe::{
+    Duplicate, NearDuplicate, collapse_duplicates, duplicate_note, find_near_duplicates,
+};
+use crate::detect::{generated_marker, minified_line_length};
+use crate::extract::ExtractorRegistry;
+use crate::filter::PathFilter;
+use crate::grep::GrepFilter;
+use crate::history::GitHistory;
+use crate::md::{
+    FileEntry, Instructions, Scan, ScanRules, SkipReason, SkippedEntry, apply_follow_deps,
+    apply_grep, estimate_tokens, scan_root, write_pack,
+};
+use crate::stats::{StatsReport, Totals, build_report};
+use crate::template::{PackFormat, Template};
+
+/// Configures and builds a [`Pack`].
+///
+/// ```no_run
+/// use pai::{PackBuilder, PackFormat};
+///
+/// let pack = PackBuilder::new()
+///     .root("src")
+///     .extensions(["rs"])
+///     .format(PackFormat::Xml)
+///     .budget(50_000)
+///     .build()?;
+/// println!("{} files, ~{} tokens", pack.totals.files, pack.totals.tokens);
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct PackBuilder {
+    roots: Vec<PathBuf>,
+    extensions: Vec<String>,
+    filter: PathFilter,
+    extractors: ExtractorRegistry,
+    include_binary: bool,
+    include_minified: bool,
+    include_generated: bool,
+    template: Template,
+    instructions: Instructions,
+    grep: Option<GrepFilter>,
+    follow_deps: Vec<String>,
+    deps_depth: usize,
+    dedupe: bool,
+    near_duplicates: Option<f64>,
+    budget: Option<usize>,
+    git_history: Option<usize>,
+    output: Option<PathBuf>,
+    sink: Option<Box<dyn Write + Send>>,
+}
+
+impl Default for PackBuilder {
+    fn default() -> Self {
+        PackBuilder {
+            roots: Vec::new(),
+            extensions: Vec::new(),
+            filter: PathFilter::default(),
+            extractors: ExtractorRegistry::with_defaults(false, 10),
+            include_binary: false,
+            include_minified: false,
+            include_generated: false,
+            template: Template::builtin(PackFormat::Markdown),
+            instructions: Instructions::default(),
+            grep: None,
+            follow_deps: Vec::new(),
+            deps_depth: 10,
+            dedupe: false,
+            near_duplicates: None,
+            budget: None,
+            git_history: None,
+            output: None,
+            sink: None,
+        }
+    }
+}
+
+impl PackBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a directory or `.tar`, `.tar.gz` or `.zip` archive to scan.
+    /// Defaults to the current directory when no root is given.
+    pub fn root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.roots.push(root.into());
+        self
+    }
+
+    /// Only packs files with these extensions (without the dot).
+    pub fn extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.extensions = extensions.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Replaces the default path filter.
+    pub fn filter(mut self, filter: PathFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Replaces the default notebook, CSV and lockfile extractors.
+    pub fn extractors(mut self, extractors: ExtractorRegistry) -> Self {
+        self.extractors = extractors;
+        self
+    }
+
+    /// Packs files that look binary as long as they decode as text.
+    pub fn include_binary(mut self, include: bool) -> Self {
+        self.include_binary = include;
+        self
+    }
+
+    /// Packs minified files and source maps instead of skipping them.
+    pub fn include_minified(mut self, include: bool) -> Self {
+        self.include_minified = include;
+        self
+    }
+
+    /// Packs files carrying generated-code markers instead of skipping them.
+    pub fn include_generated(mut self, include: bool) -> Self {
+        self.include_generated = include;
+        self
+    }
+
+    pub fn format(mut self, format: PackFormat) -> Self {
+        self.template = Template::builtin(format);
+        self
+    }
+
+    /// Renders the pack with a custom template instead of a built-in format.
+    pub fn template(mut self, template: Template) -> Self {
+        self.template = template;
+        self
+    }
+
+    pub fn instructions(mut self, instructions: Instructions) -> Self {
+        self.instructions = instructions;
+        self
+    }
+
+    pub fn grep(mut self, grep: GrepFilter) -> Self {
+        self.grep = Some(grep);
+        self
+    }
+
+    /// Keeps only files reachable from `entries` through local imports.
+    pub fn follow_deps(mut self, entries: Vec<String>, max_depth: usize) -> Self {
+        self.follow_deps = entries;
+        self.deps_depth = max_depth;
+        self
+    }
+
+    pub fn dedupe(mut self, dedupe: bool) -> Self {
+        self.dedupe = dedupe;
+        self
+    }
+
+    pub fn near_duplicates(mut self, threshold: f64) -> Self {
+        self.near_duplicates = Some(threshold);
+        self
+    }
+
+    /// Caps the estimated tokens of packed file contents. Files are taken in
+    /// pack order and any file that no longer fits is skipped.
+    pub fn budget(mut self, tokens: usize) -> Self {
+        self.budget = Some(tokens);
+        self
+    }
+
+    /// Appends the last `limit` commits touching the packed files and annotates
+    /// each file with its last change. Requires the roots to be in a git repository.
+    pub fn git_history(mut self, limit: usize) -> Self {
+        self.git_history = Some(limit);
+        self
+    }
+
+    /// Path of the file the pack is written to, so it is never packed into itself.
+    pub fn output(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output = Some(path.into());
+        self
+    }
+
+    /// Writer that receives the rendered pack when [`PackBuilder::build`] runs.
+    pub fn sink(mut self, sink: impl Write + Send + 'static) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    fn scan(&self) -> Result<Scan> {
+        let rules = ScanRules::new(
+            self.extensions.clone(),
+            self.filter.clone(),
+            self.include_binary,
+        );
+        let roots = if self.roots.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.roots.clone()
+        };
+
+        let mut scan = Scan::default();
+        for root in &roots {
+            let mut root_scan = scan_root(root, &rules, self.output.as_deref())?;
+            // With several roots, paths keep their root so they stay unambiguous
+            if roots.len() > 1 {
+                prefix_paths(&mut root_scan, root);
+            }
+            scan.files.append(&mut root_scan.files);
+            scan.skipped.append(&mut root_scan.skipped);
+        }
+        Ok(scan)
+    }
+
+    /// Skips minified and generated files. Files rewritten by an extractor
+    /// are kept, since their packed form is already a compact summary.
+    fn apply_content_checks(&self, scan: &mut Scan, extracted: &[(String, &'static str)]) {
+        let (kept, skipped): (Vec<_>, Vec<_>) = std::mem::take(&mut scan.files)
+            .into_iter()
+            .map(|file| {
+                let reason = if extracted
+                    .iter()
+                    .any(|(path, _)| *path == file.relative_path)
+                {
+                    None
+                } else if let Some(marker) =
+                    generated_marker(&file.path, &file.content).filter(|_| !self.include_generated)
+                {
+                    Some(SkipReason::Generated(marker.to_string()))
+                } else {
+                    minified_line_length(&file.path, &file.content)
+                        .filter(|_| !self.include_minified)
+                        .map(SkipReason::Minified)
+                };
+                (file, reason)
+            })
+            .partition(|(_, reason)| reason.is_none());
+
+        scan.files = kept.into_iter().map(|(file, _)| file).collect();
+        scan.skipped
+            .extend(skipped.into_iter().filter_map(|(file, reason)| {
+                Some(SkippedEntry {
+                    size: file.content.len() as u64,
+                    relative_path: file.relative_path,
+                    is_dir: false,
+                    reason: reason?,
+                })
+            }));
+    }
+
+    /// Scans the roots, applies every selection step and renders the pack to
+    /// the sink, if one was set.
+    pub fn build(mut self) -> Result<Pack> {
+        let mut scan = self.scan()?;
+        let extracted = self.extractors.apply(&mut scan.files);
+        self.apply_content_checks(&mut scan, &extracted);
+
+        if !self.follow_deps.is_empty() {
+            let input_dir = match self.roots.as_slice() {
+                [root] => root.to_string_lossy().to_string(),
+                _ => String::new(),
+            };
+            apply_follow_deps(&mut scan, &self.follow_deps, &input_dir, self.deps_depth)?;
+        }
+
+        if let Some(grep) = &self.grep {
+            apply_grep(&mut scan, grep);
+        }
+
+        // Budget first, so duplicates only ever point at files that were kept
+        if let Some(budget) = self.budget {
+            apply_budget(&mut scan, budget, self.dedupe);
+        }
+
+        let duplicates = if self.dedupe {
+            collapse_duplicates(&mut scan.files)
+        } else {
+            Vec::new()
+        };
+        let near_duplicates = self
+            .near_duplicates
+            .map(|threshold| find_near_duplicates(&scan.files, &duplicates, threshold))
+            .unwrap_or_default();
+
+        let mut template = self.template;
+        let history = match self.git_history {
+            Some(limit) => {
+                // Paths are relative to a single root, or to the current directory otherwise
+                let repo = match self.roots.as_slice() {
+                    [root] => root.clone(),
+                    _ => PathBuf::from("."),
+                };
+                template = template.with_history();
+                Some(GitHistory::load(&repo, &scan.files, limit)?)
+            }
+            None => None,
+        };
+
+        let mut totals = Totals::default();
+        for file in &scan.files {
+            totals.add(file.content.len(), file.tokens());
+        }
+
+        let pack = Pack {
+            files: scan.files,
+            skipped: scan.skipped,
+            extracted,
+            duplicates,
+            near_duplicates,
+            totals,
+            history,
+            template,
+            instructions: self.instructions,
+        };
+
+        if let Some(sink) = &mut self.sink {
+            pack.render(sink)?;
+            sink.flush()?;
+        }
+        Ok(pack)
+    }
+}
+
+fn prefix_paths(scan: &mut Scan, root: &Path) {
+    let root: PathBuf = root
+        .components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect();
+    let prefixed = |path: &str| root.join(path).to_string_lossy().to_string();
+
+    for file in &mut scan.files {
+        file.relative_path = prefixed(&file.relative_path);
+    }
+    for skipped in &mut scan.skipped {
+        skipped.relative_path = prefixed(&skipped.relative_path);
+    }
+}
+
+/// Keeps files in order while their estimated tokens fit within `budget`.
+///
+/// With `dedupe`, a copy of a kept file costs what its duplicate note will.
+fn apply_budget(scan: &mut Scan, budget: usize, dedupe: bool) {
+    let mut used = 0;
+    // Content of each kept file, with the path a later copy will point at
+    let mut kept_content: HashMap<&str, &str> = HashMap::new();
+    let keep: Vec<bool> = scan
+        .files
+        .iter()
+        .map(|file| {
+            let mut tokens = file.tokens();
+            if let Some(original) = kept_content.get(file.content.as_str()) {
+                tokens = tokens.min(estimate_tokens(&duplicate_note(original)));
+            }
+            let fits = used + tokens <= budget;
+            if fits {
+                used += tokens;
+                if dedupe {
+                    kept_content
+                        .entry(&file.content)
+                        .or_insert(&file.relative_path);
+                }
+            }
+            fits
+        })
+        .collect();
+
+    let mut keep = keep.into_iter();
+    let (kept, over): (Vec<_>, Vec<_>) = std::mem::take(&mut scan.files)
+        .into_iter()
+        .partition(|_| keep.next().unwrap_or(false));
+
+    scan.files = kept;
+    scan.skipped
+        .extend(over.into_iter().map(|file| SkippedEntry {
+            size: file.content.len() as u64,
+            reason: SkipReason::OverBudget(file.tokens()),
+            relative_path: file.relative_path,
+            is_dir: false,
+        }));
+}
+
+/// The result of a [`PackBuilder`]: the files to pack, everything left out and why, and totals.
+pub struct Pack {
+    pub files: Vec<FileEntry>,
+    pub skipped: Vec<SkippedEntry>,
+    /// `(path, extractor)` for each file converted by an extractor.
+    pub extracted: Vec<(String, &'static str)>,
+    pub duplicates: Vec<Duplicate>,
+    pub near_duplicates: Vec<NearDuplicate>,
+    pub totals: Totals,
+    pub history: Option<GitHistory>,
+    template: Template,
+    instructions: Instructions,
+}
+
+impl Pack {
+    /// Writes the prompt, rendered files, git history and suffix to `out`.
+    pub fn render<W: Write>(&self, out: &mut W) -> Result<()> {
+        write_pack(
+            out,
+            &self.template,
+            &self.files,
+            &self.instructions,
+            self.history.as_ref(),
+        )
+    }
+
+    /// Language, directory and exclusion breakdown listing the `top` largest files.
+    pub fn report(&self, top: usize) -> StatsReport {
+        build_report(&self.files, &self.skipped, top)
+    }
+
+    pub fn tokens_saved(&self) -> usize {
+        self.duplicates.iter().map(|d| d.tokens_saved).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_builder_is_send() {
+        // Services hold builders across `.await`.
+        fn assert_send<T: Send>() {}
+        assert_send::<PackBuilder>();
+        assert_send::<Pack>();
+    }
+
+    #[test]
+    fn test_builder_applies_budget_and_renders() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "a".repeat(40)).unwrap();
+        fs::write(dir.path().join("b.rs"), "b".repeat(80)).unwrap();
+        fs::write(dir.path().join("c.rs"), "c".repeat(20)).unwrap();
+        fs::write(dir.path().join("notes.txt"), "skip me").unwrap();
+
+        let pack = PackBuilder::new()
+            .root(dir.path())
+            .extensions(["rs"])
+            .budget(20)
+            .build()
+            .unwrap();
+
+        let files: Vec<&str> = pack
+            .files
+            .iter()
+            .map(|f| f.relative_path.as_str())
+            .collect();
+        assert_eq!(files, vec!["a.rs", "c.rs"]);
+        assert_eq!(pack.totals.tokens, 15);
+        assert!(
+            pack.skipped
+                .iter()
+                .any(|s| s.relative_path == "b.rs" && s.reason.rule() == "budget")
+        );
+
+        let mut out = Vec::new();
+        pack.render(&mut out).unwrap();
+        assert!(
+            String::from_utf8(out)
+                .unwrap()
+                .starts_with("a.rs\n```\naaaa")
+        );
+    }
+
+    #[test]
+    fn test_budget_keeps_duplicates_with_their_original() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "a".repeat(400)).unwrap();
+        fs::write(dir.path().join("b.rs"), "a".repeat(400)).unwrap();
+        fs::write(dir.path().join("c.rs"), "c".repeat(40)).unwrap();
+
+        let build = |budget| {
+            PackBuilder::new()
+                .root(dir.path())
+                .dedupe(true)
+                .near_duplicates(0.5)
+                .budget(budget)
+                .build()
+                .unwrap()
+        };
+        let paths = |pack: &Pack| {
+            pack.files
+                .iter()
+                .map(|f| f.relative_path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // The original does not fit, so neither may a note pointing at it
+        let pack = build(50);
+        assert_eq!(paths(&pack), vec!["c.rs"]);
+        assert!(pack.duplicates.is_empty());
+        assert!(pack.near_duplicates.is_empty());
+
+        // Once it fits, its copy costs only the note
+        let pack = build(130);
+        assert_eq!(paths(&pack), vec!["a.rs", "b.rs", "c.rs"]);
+        assert_eq!(pack.duplicates[0].original, "a.rs");
+    }
+
+    #[test]
+    fn test_builder_prefixes_multiple_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["one", "two"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("lib.rs"), name).unwrap();
+        }
+
+        let pack = PackBuilder::new()
+            .root(dir.path().join("one"))
+            .root(dir.path().join("two"))
+            .build()
+            .unwrap();
+
+        assert_eq!(pack.files.len(), 2);
+        assert!(pack.files[0].relative_path.ends_with("one/lib.rs"));
+        assert!(pack.files[1].relative_path.ends_with("two/lib.rs"));
+    }
+}
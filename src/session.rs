@@ -0,0 +1,561 @@
+//! Saved `pai code` conversations: one JSONL file per session, grouped by
+//! project under the user's data directory.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local};
+use clap::{Args, Subcommand};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::models::{ContentBlock, Message};
+
+/// Overrides where sessions are stored.
+pub const DATA_DIR_ENV: &str = "PAI_DATA_DIR";
+
+/// One line of a session file.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record {
+    /// Always the first line.
+    Meta {
+        id: String,
+        created: DateTime<Local>,
+        root: PathBuf,
+        model: String,
+    },
+    /// A conversation message, including tool calls and their results.
+    Message {
+        at: DateTime<Local>,
+        message: Message,
+    },
+}
+
+/// `$PAI_DATA_DIR`, else the platform's per-user data directory plus `pai`.
+pub fn data_dir() -> Result<PathBuf> {
+    if let Some(dir) = env::var_os(DATA_DIR_ENV) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir).join("pai"));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        return Ok(PathBuf::from(home).join(".local/share/pai"));
+    }
+    if let Some(dir) = env::var_os("APPDATA") {
+        return Ok(PathBuf::from(dir).join("pai"));
+    }
+    bail!(
+        "Cannot find a data directory for sessions; set {}",
+        DATA_DIR_ENV
+    )
+}
+
+/// Directory name for a project: its canonical path with separators and
+/// other punctuation turned into dashes, plus a hash of the exact path so
+/// `/a/my-app` and `/a/my/app` do not share sessions.
+fn project_key(root: &Path) -> String {
+    let path = root.to_string_lossy();
+    let slug: String = path
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    // FNV-1a: stable across builds, unlike the std hasher.
+    let hash = path.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{}-{:08x}", slug, hash as u32)
+}
+
+fn check_id(id: &str) -> Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        bail!("Invalid session id: {}", id);
+    }
+    Ok(())
+}
+
+/// Identifies a message so a rewrite can find the time it was first saved.
+fn message_key(message: &Message) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(message)?.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
+/// Text of a message's first text block, on one line.
+fn first_text(message: &Message) -> Option<String> {
+    message.content.iter().find_map(|block| match block {
+        ContentBlock::Text { text } => Some(text.split_whitespace().collect::<Vec<_>>().join(" ")),
+        _ => None,
+    })
+}
+
+/// A stored message and when it was first saved.
+type Stamped = (DateTime<Local>, Message);
+
+/// Summary of a stored session, for listings.
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub id: String,
+    pub created: DateTime<Local>,
+    pub updated: DateTime<Local>,
+    pub model: String,
+    pub messages: usize,
+    /// The first thing the user asked.
+    pub title: String,
+}
+
+/// An open session that conversation messages are appended to.
+#[derive(Debug)]
+pub struct Session {
+    pub id: String,
+    path: PathBuf,
+    created: DateTime<Local>,
+    root: PathBuf,
+    model: String,
+    /// Key and save time of each conversation message already on disk.
+    saved: Vec<(u64, DateTime<Local>)>,
+    /// The file still holds messages dropped from the conversation when it was
+    /// opened, so the next save rewrites it.
+    trimmed: bool,
+}
+
+impl Session {
+    fn meta(&self) -> Record {
+        Record::Meta {
+            id: self.id.clone(),
+            created: self.created,
+            root: self.root.clone(),
+            model: self.model.clone(),
+        }
+    }
+
+    fn write_message(
+        file: &mut File,
+        message: &Message,
+        at: DateTime<Local>,
+    ) -> Result<(u64, DateTime<Local>)> {
+        let record = Record::Message {
+            at,
+            message: message.clone(),
+        };
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok((message_key(message)?, at))
+    }
+
+    /// Appends the messages added to `conversation` since the last save. The
+    /// file is created on the first message, so empty sessions leave no trace.
+    pub fn save(&mut self, conversation: &[Message]) -> Result<()> {
+        if conversation.len() <= self.saved.len() {
+            return Ok(());
+        }
+        if self.trimmed || (self.saved.is_empty() && !self.path.exists()) {
+            return self.rewrite(conversation);
+        }
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open session file {}", self.path.display()))?;
+        let now = Local::now();
+        for message in &conversation[self.saved.len()..] {
+            let saved = Self::write_message(&mut file, message, now)?;
+            self.saved.push(saved);
+        }
+        Ok(())
+    }
+
+    /// Replaces the stored conversation, e.g. after it was compacted. Messages
+    /// that were saved before keep their original time.
+    pub fn rewrite(&mut self, conversation: &[Message]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let temp = self.path.with_extension("jsonl.tmp");
+        let mut file = File::create(&temp)
+            .with_context(|| format!("Failed to write session file {}", temp.display()))?;
+        writeln!(file, "{}", serde_json::to_string(&self.meta())?)?;
+
+        // Kept messages stay in order, so each is looked for after the last match.
+        let now = Local::now();
+        let mut next = 0;
+        let mut saved = Vec::with_capacity(conversation.len());
+        for message in conversation {
+            let key = message_key(message)?;
+            let at = match self.saved[next..].iter().position(|(k, _)| *k == key) {
+                Some(offset) => {
+                    next += offset + 1;
+                    self.saved[next - 1].1
+                }
+                None => now,
+            };
+            saved.push(Self::write_message(&mut file, message, at)?);
+        }
+        fs::rename(&temp, &self.path)?;
+        self.saved = saved;
+        self.trimmed = false;
+        Ok(())
+    }
+}
+
+/// The sessions of one project.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    dir: PathBuf,
+}
+
+impl SessionStore {
+    /// Store whose session files live directly in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        SessionStore { dir: dir.into() }
+    }
+
+    /// Store for the project rooted at `root`, under [`data_dir`].
+    pub fn for_project(root: &Path) -> Result<Self> {
+        let root = root
+            .canonicalize()
+            .with_context(|| format!("Failed to resolve {}", root.display()))?;
+        Ok(Self::new(
+            data_dir()?.join("sessions").join(project_key(&root)),
+        ))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.jsonl", id))
+    }
+
+    /// Starts a new session; nothing is written until the first message.
+    pub fn create(&self, root: &Path, model: &str) -> Session {
+        let created = Local::now();
+        let suffix = uuid::Uuid::new_v4().simple().to_string();
+        let id = format!("{}-{}", created.format("%Y%m%d-%H%M%S"), &suffix[..4]);
+        Session {
+            path: self.path(&id),
+            id,
+            created,
+            root: root.to_path_buf(),
+            model: model.to_string(),
+            saved: Vec::new(),
+            trimmed: false,
+        }
+    }
+
+    /// The full id for `id` or a unique prefix of one.
+    pub fn find(&self, id: &str) -> Result<String> {
+        check_id(id)?;
+        if self.path(id).exists() {
+            return Ok(id.to_string());
+        }
+        let matches: Vec<String> = self
+            .list()?
+            .into_iter()
+            .map(|info| info.id)
+            .filter(|candidate| candidate.starts_with(id))
+            .collect();
+        match matches.as_slice() {
+            [only] => Ok(only.clone()),
+            [] => bail!("No session '{}' in {}", id, self.dir.display()),
+            _ => bail!("Session id '{}' is ambiguous: {}", id, matches.join(", ")),
+        }
+    }
+
+    /// The session's header and its messages with the time each was saved.
+    fn read(&self, id: &str) -> Result<(Record, Vec<Stamped>)> {
+        let path = self.path(id);
+        let file = File::open(&path)
+            .with_context(|| format!("Failed to open session file {}", path.display()))?;
+        let mut meta = None;
+        let mut messages = Vec::new();
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // A crash can leave a partial last line; keep what parsed.
+            match serde_json::from_str::<Record>(&line) {
+                Ok(Record::Message { at, message }) => messages.push((at, message)),
+                Ok(record) => meta = meta.or(Some(record)),
+                Err(e) => eprintln!(
+                    "Warning: skipping unreadable line {} of {}: {}",
+                    index + 1,
+                    path.display(),
+                    e
+                ),
+            }
+        }
+        let meta = meta.with_context(|| format!("{} has no session header", path.display()))?;
+        Ok((meta, messages))
+    }
+
+    /// Reopens session `id` (or a unique prefix), returning it with its conversation.
+    ///
+    /// Trailing messages that left a tool call unanswered (an interrupted turn)
+    /// are dropped so the conversation can continue with a new user message.
+    /// The file keeps them until the conversation is saved again.
+    pub fn open(&self, id: &str) -> Result<(Session, Vec<Message>)> {
+        let id = self.find(id)?;
+        let (meta, records) = self.read(&id)?;
+        let Record::Meta {
+            created,
+            root,
+            model,
+            ..
+        } = meta
+        else {
+            unreachable!("read returns the meta record")
+        };
+
+        let (times, mut messages): (Vec<_>, Vec<_>) = records.into_iter().unzip();
+        let stored = messages.len();
+        while let Some(last) = messages.last() {
+            let finished = last.role == "assistant"
+                && !last
+                    .content
+                    .iter()
+                    .any(|b| matches!(b, ContentBlock::ToolUse { .. }));
+            if finished {
+                break;
+            }
+            messages.pop();
+        }
+
+        let session = Session {
+            path: self.path(&id),
+            id,
+            created,
+            root,
+            model,
+            saved: times
+                .into_iter()
+                .zip(&messages)
+                .map(|(at, message)| Ok((message_key(message)?, at)))
+                .collect::<Result<_>>()?,
+            trimmed: messages.len() != stored,
+        };
+        Ok((session, messages))
+    }
+
+    /// Sessions in this store, most recently updated first.
+    pub fn list(&self) -> Result<Vec<SessionInfo>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut sessions = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "jsonl") {
+                continue;
+            }
+            let Some(id) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else {
+                continue;
+            };
+            let Ok((Record::Meta { created, model, .. }, messages)) = self.read(&id) else {
+                continue;
+            };
+            let updated = fs::metadata(&path)
+                .and_then(|m| m.modified())
+                .map(DateTime::<Local>::from)
+                .unwrap_or(created);
+            sessions.push(SessionInfo {
+                title: messages
+                    .iter()
+                    .map(|(_, m)| m)
+                    .filter(|m| m.role == "user")
+                    .find_map(first_text)
+                    .unwrap_or_default(),
+                id,
+                created,
+                updated,
+                model,
+                messages: messages.len(),
+            });
+        }
+        sessions.sort_by(|a, b| b.updated.cmp(&a.updated).then(b.id.cmp(&a.id)));
+        Ok(sessions)
+    }
+
+    /// The most recently updated session.
+    pub fn latest(&self) -> Result<Option<SessionInfo>> {
+        Ok(self.list()?.into_iter().next())
+    }
+
+    pub fn delete(&self, id: &str) -> Result<String> {
+        let id = self.find(id)?;
+        fs::remove_file(self.path(&id))?;
+        Ok(id)
+    }
+
+    /// Human-readable transcript of session `id`.
+    pub fn transcript(&self, id: &str) -> Result<String> {
+        let id = self.find(id)?;
+        let (_, messages) = self.read(&id)?;
+        let mut out = String::new();
+        for (_, message) in &messages {
+            for block in &message.content {
+                let line = match block {
+                    ContentBlock::Text { text } if message.role == "user" => {
+                        format!("\x1b[94mYou\x1b[0m: {}", text)
+                    }
+                    ContentBlock::Text { text } => format!("\x1b[93mAssistant\x1b[0m: {}", text),
+                    ContentBlock::ToolUse { name, input, .. } => {
+                        format!("\x1b[92mtool\x1b[0m: {}({})", name, input)
+                    }
+                    ContentBlock::ToolResult { content, error, .. } => {
+                        let label = if error.unwrap_or(false) {
+                            "tool_error"
+                        } else {
+                            "tool_output"
+                        };
+                        let mut preview: String = content.chars().take(300).collect();
+                        if preview.len() < content.len() {
+                            preview.push_str("...");
+                        }
+                        format!("\x1b[32m{}\x1b[0m: {}", label, preview)
+                    }
+                };
+                out.push_str(&line);
+                out.push('\n');
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct SessionsArgs {
+    #[command(subcommand)]
+    pub command: SessionsCommand,
+
+    /// Project whose sessions to manage (defaults to the current directory).
+    #[arg(long, global = true)]
+    pub root: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionsCommand {
+    /// List saved sessions, most recent first
+    List,
+    /// Print a session's conversation
+    Show {
+        /// Session id or a unique prefix of one
+        id: String,
+    },
+    /// Delete a saved session
+    Delete {
+        /// Session id or a unique prefix of one
+        id: String,
+    },
+}
+
+pub fn run_sessions(args: SessionsArgs) -> Result<()> {
+    let root = args.root.unwrap_or_else(|| PathBuf::from("."));
+    let store = SessionStore::for_project(&root)?;
+
+    match args.command {
+        SessionsCommand::List => {
+            let sessions = store.list()?;
+            if sessions.is_empty() {
+                println!("No sessions saved for this project.");
+            }
+            for session in sessions {
+                let title: String = session.title.chars().take(60).collect();
+                println!(
+                    "{}  {}  {:>4} messages  {:<8}  {}",
+                    session.id,
+                    session.updated.format("%Y-%m-%d %H:%M"),
+                    session.messages,
+                    session.model,
+                    title
+                );
+            }
+        }
+        SessionsCommand::Show { id } => print!("{}", store.transcript(&id)?),
+        SessionsCommand::Delete { id } => {
+            let id = store.delete(&id)?;
+            println!("Deleted session {}", id);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(role: &str, text: &str) -> Message {
+        Message {
+            role: role.to_string(),
+            content: vec![ContentBlock::Text {
+                text: text.to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SessionStore::new(dir.path());
+        let mut session = store.create(Path::new("/project"), "Claude");
+        session.save(&[]).unwrap();
+        assert!(store.list().unwrap().is_empty());
+
+        let mut conversation = vec![text("user", "fix the\nbug"), text("assistant", "done")];
+        session.save(&conversation).unwrap();
+        conversation.push(text("user", "thanks"));
+        conversation.push(Message {
+            role: "assistant".to_string(),
+            content: vec![ContentBlock::ToolUse {
+                id: "1".to_string(),
+                name: "read_file".to_string(),
+                input: serde_json::json!({}),
+            }],
+        });
+        session.save(&conversation).unwrap();
+
+        // Rewriting, as compaction does, keeps the time each message was saved.
+        let times = |id: &str| -> Vec<_> {
+            let (_, records) = store.read(id).unwrap();
+            records.into_iter().map(|(at, _)| at).collect()
+        };
+        let before = times(&session.id);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        session.rewrite(&conversation[1..]).unwrap();
+        assert_eq!(times(&session.id), before[1..]);
+        session.rewrite(&conversation).unwrap();
+        assert!(times(&session.id)[0] > before[0]);
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].title, "fix the bug");
+        assert_eq!(listed[0].messages, 4);
+
+        // The unanswered tool call and the message that led to it are dropped,
+        // but only from the file once the conversation goes on.
+        let (mut session, mut messages) = store.open(&session.id[..8]).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(store.list().unwrap()[0].messages, 4);
+        session.save(&messages).unwrap();
+        assert_eq!(store.list().unwrap()[0].messages, 4);
+        messages.push(text("user", "go on"));
+        session.save(&messages).unwrap();
+        assert_eq!(store.list().unwrap()[0].messages, 3);
+
+        store.delete(&session.id).unwrap();
+        assert!(store.latest().unwrap().is_none());
+
+        assert_ne!(
+            project_key(Path::new("/a/my-app")),
+            project_key(Path::new("/a/my/app"))
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use anyhow::{Context, Result};
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::md::{FileEntry, ScanArgs, SkippedEntry};
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    /// Pack file that `pai md` would write; it is excluded like during packing.
+    #[arg(short, long, default_value = "llm.md")]
+    pub output: String,
+
+    /// Report format.
+    #[arg(short, long, value_enum, default_value_t = StatsFormat::Text)]
+    pub format: StatsFormat,
+
+    /// Number of largest files to list.
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatsFormat {
+    Text,
+    Json,
+}
+
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Totals {
+    pub files: usize,
+    pub bytes: usize,
+    pub tokens: usize,
+}
+
+impl Totals {
+    pub(crate) fn add(&mut self, bytes: usize, tokens: usize) {
+        self.files += 1;
+        self.bytes += bytes;
+        self.tokens += tokens;
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Breakdown {
+    pub name: String,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+#[derive(Serialize, Debug)]
+pub struct FileStat {
+    pub path: String,
+    pub language: String,
+    pub bytes: usize,
+    pub tokens: usize,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExcludedEntry {
+    pub path: String,
+    pub is_dir: bool,
+    pub bytes: u64,
+    pub reason: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ExclusionGroup {
+    pub rule: String,
+    pub entries: Vec<ExcludedEntry>,
+}
+
+/// Summary of what a pack built from a scan would contain.
+#[derive(Serialize, Debug)]
+pub struct StatsReport {
+    pub totals: Totals,
+    pub languages: Vec<Breakdown>,
+    pub directories: Vec<Breakdown>,
+    pub largest: Vec<FileStat>,
+    pub excluded: Vec<ExclusionGroup>,
+}
+
+fn top_level_dir(relative_path: &str) -> String {
+    let mut components = Path::new(relative_path).components();
+    match (components.next(), components.next()) {
+        (Some(first), Some(_)) => first.as_os_str().to_string_lossy().to_string(),
+        _ => ".".to_string(),
+    }
+}
+
+fn sorted_breakdown(map: BTreeMap<String, Totals>) -> Vec<Breakdown> {
+    let mut breakdown: Vec<Breakdown> = map
+        .into_iter()
+        .map(|(name, totals)| Breakdown { name, totals })
+        .collect();
+    breakdown.sort_by_key(|b| std::cmp::Reverse(b.totals.tokens));
+    breakdown
+}
+
+pub fn build_report(files: &[FileEntry], skipped: &[SkippedEntry], top: usize) -> StatsReport {
+    let mut totals = Totals::default();
+    let mut languages: BTreeMap<String, Totals> = BTreeMap::new();
+    let mut directories: BTreeMap<String, Totals> = BTreeMap::new();
+    let mut largest = Vec::new();
+
+    for file in files {
+        let bytes = file.content.len();
+        let tokens = file.tokens();
+        let language = file.language();
+
+        totals.add(bytes, tokens);
+        languages
+            .entry(language.to_string())
+            .or_default()
+            .add(bytes, tokens);
+        directories
+            .entry(top_level_dir(&file.relative_path))
+            .or_default()
+            .add(bytes, tokens);
+        largest.push(FileStat {
+            path: file.relative_path.clone(),
+            language: language.to_string(),
+            bytes,
+            tokens,
+        });
+    }
+
+    largest.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+    largest.truncate(top);
+
+    let mut groups: BTreeMap<&'static str, Vec<ExcludedEntry>> = BTreeMap::new();
+    for skipped in skipped {
+        groups
+            .entry(skipped.reason.rule())
+            .or_default()
+            .push(ExcludedEntry {
+                path: skipped.relative_path.clone(),
+                is_dir: skipped.is_dir,
+                bytes: skipped.size,
+                reason: skipped.reason.to_string(),
+            });
+    }
+
+    StatsReport {
+        totals,
+        languages: sorted_breakdown(languages),
+        directories: sorted_breakdown(directories),
+        largest,
+        excluded: groups
+            .into_iter()
+            .map(|(rule, entries)| ExclusionGroup {
+                rule: rule.to_string(),
+                entries,
+            })
+            .collect(),
+    }
+}
+
+fn write_breakdown(out: &mut String, title: &str, rows: &[Breakdown]) {
+    out.push_str(&format!("\n{}\n", title));
+    for row in rows {
+        out.push_str(&format!(
+            "  {:<24} {:>6} files {:>10} bytes {:>9} tokens\n",
+            row.name, row.totals.files, row.totals.bytes, row.totals.tokens
+        ));
+    }
+}
+
+pub fn render_text(report: &StatsReport) -> String {
+    let mut out = format!(
+        "Total: {} files, {} bytes, ~{} tokens\n",
+        report.totals.files, report.totals.bytes, report.totals.tokens
+    );
+
+    write_breakdown(&mut out, "By language:", &report.languages);
+    write_breakdown(&mut out, "By top-level directory:", &report.directories);
+
+    out.push_str("\nLargest files:\n");
+    for file in &report.largest {
+        out.push_str(&format!(
+            "  {:<48} {:>10} bytes {:>9} tokens\n",
+            file.path, file.bytes, file.tokens
+        ));
+    }
+
+    out.push_str("\nExcluded:\n");
+    for group in &report.excluded {
+        out.push_str(&format!("  {} ({})\n", group.rule, group.entries.len()));
+        for entry in &group.entries {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            out.push_str(&format!("    {}{}: {}\n", entry.path, suffix, entry.reason));
+        }
+    }
+
+    out
+}
+
+pub fn print_stats(args: StatsArgs) -> Result<()> {
+    let config = Config::load(Path::new("."))?;
+    let pack = args
+        .scan
+        .pack_builder(&config)
+        .output(&args.output)
+        .build()?;
+    let report = pack.report(args.top);
+
+    match args.format {
+        StatsFormat::Text => print!("{}", render_text(&report)),
+        StatsFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).context("Failed to serialize stats report")?
+        ),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterReason;
+    use crate::md::SkipReason;
+
+    #[test]
+    fn test_build_report_groups_files() {
+        let files = vec![
+            FileEntry::fixture("src/main.rs", "fn main() {}"),
+            FileEntry::fixture("src/lib.rs", "pub mod a;"),
+            FileEntry::fixture("README.md", "# Title"),
+        ];
+        let skipped = vec![SkippedEntry {
+            relative_path: "target".to_string(),
+            is_dir: true,
+            size: 0,
+            reason: SkipReason::Filtered(FilterReason::Excluded("target".to_string())),
+        }];
+
+        let report = build_report(&files, &skipped, 1);
+
+        assert_eq!(report.totals.files, 3);
+        assert_eq!(report.totals.bytes, 29);
+        assert_eq!(report.languages[0].name, "Rust");
+        assert_eq!(report.languages[0].totals.files, 2);
+        assert_eq!(report.directories[0].name, "src");
+        assert!(report.directories.iter().any(|d| d.name == "."));
+        assert_eq!(report.largest.len(), 1);
+        assert_eq!(report.largest[0].path, "src/main.rs");
+        assert_eq!(report.excluded[0].rule, "excluded-name");
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::io::Write;
+
+use crate::history::GitHistory;
+use crate::md::{FileEntry, estimate_tokens, language_for};
+
+const LOOP_START: &str = "#files";
+const LOOP_END: &str = "/files";
+
+/// Variables available anywhere in a template.
+const GLOBAL_VARIABLES: &[&str] = &["file_count", "total_tokens"];
+
+/// Variables available inside the `{{#files}}...{{/files}}` loop.
+const FILE_VARIABLES: &[&str] = &[
+    "path",
+    "language",
+    "content",
+    "index",
+    "tokens",
+    "last_change",
+];
+
+const MARKDOWN_TEMPLATE: &str = "{{#files}}{{path}}\n```\n{{content}}\n```\n\n{{/files}}";
+
+const XML_TEMPLATE: &str = "<files>\n{{#files}}<file path=\"{{path}}\" language=\"{{language}}\">\n{{content}}\n</file>\n{{/files}}</files>\n";
+
+const MARKDOWN_HISTORY_TEMPLATE: &str =
+    "{{#files}}{{path}} (last changed in {{last_change}})\n```\n{{content}}\n```\n\n{{/files}}";
+
+const XML_HISTORY_TEMPLATE: &str = "<files>\n{{#files}}<file path=\"{{path}}\" language=\"{{language}}\" last_change=\"{{last_change}}\">\n{{content}}\n</file>\n{{/files}}</files>\n";
+
+/// Built-in pack formats, each backed by a default template.
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PackFormat {
+    Markdown,
+    Xml,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Variable(String),
+}
+
+/// A pack template: a header, a section repeated for every file, and a footer.
+///
+/// Templates use `{{name}}` placeholders. The per-file section is delimited by
+/// `{{#files}}` and `{{/files}}`; text before it is the header, text after it the footer.
+#[derive(Debug, Clone)]
+pub struct Template {
+    header: Vec<Segment>,
+    file: Vec<Segment>,
+    footer: Vec<Segment>,
+    /// Set for built-in templates, which have a variant annotated with git history.
+    builtin: Option<PackFormat>,
+}
+
+/// Escapes a value for use inside a double-quoted XML attribute.
+fn xml_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Wraps text in a CDATA section, splitting any `]]>` it contains.
+fn xml_cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+fn parse_segments(source: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        if start > 0 {
+            segments.push(Segment::Text(rest[..start].to_string()));
+        }
+        let Some(end) = rest[start..].find("}}") else {
+            bail!("Unclosed '{{{{' in template");
+        };
+        let name = rest[start + 2..start + end].trim();
+        segments.push(Segment::Variable(name.to_string()));
+        rest = &rest[start + end + 2..];
+    }
+
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest.to_string()));
+    }
+
+    Ok(segments)
+}
+
+fn is_marker(segment: &Segment, marker: &str) -> bool {
+    matches!(segment, Segment::Variable(name) if name == marker)
+}
+
+fn check_variables(segments: &[Segment], allowed: &[&[&str]], section: &str) -> Result<()> {
+    for segment in segments {
+        if let Segment::Variable(name) = segment
+            && !allowed.iter().any(|vars| vars.contains(&name.as_str()))
+        {
+            bail!(
+                "Unknown template variable '{{{{{}}}}}' in {}",
+                name,
+                section
+            );
+        }
+    }
+    Ok(())
+}
+
+impl Template {
+    /// Parses a template containing exactly one `{{#files}}...{{/files}}` loop.
+    pub fn parse(source: &str) -> Result<Self> {
+        let segments = parse_segments(source)?;
+        let Some(start) = segments.iter().position(|s| is_marker(s, LOOP_START)) else {
+            bail!("Template is missing a '{{{{#files}}}}' section");
+        };
+        let Some(end) = segments.iter().position(|s| is_marker(s, LOOP_END)) else {
+            bail!("Template is missing a closing '{{{{/files}}}}'");
+        };
+        if end < start {
+            bail!("Template closes '{{{{/files}}}}' before opening it");
+        }
+
+        Self::from_segments(
+            segments[..start].to_vec(),
+            segments[start + 1..end].to_vec(),
+            segments[end + 1..].to_vec(),
+        )
+    }
+
+    /// Builds a template from separate header, per-file and footer sections.
+    pub fn from_sections(header: &str, file: &str, footer: &str) -> Result<Self> {
+        Self::from_segments(
+            parse_segments(header)?,
+            parse_segments(file)?,
+            parse_segments(footer)?,
+        )
+    }
+
+    fn from_segments(
+        header: Vec<Segment>,
+        file: Vec<Segment>,
+        footer: Vec<Segment>,
+    ) -> Result<Self> {
+        check_variables(&header, &[GLOBAL_VARIABLES], "header")?;
+        check_variables(&file, &[GLOBAL_VARIABLES, FILE_VARIABLES], "files section")?;
+        check_variables(&footer, &[GLOBAL_VARIABLES], "footer")?;
+        Ok(Template {
+            header,
+            file,
+            footer,
+            builtin: None,
+        })
+    }
+
+    pub fn builtin(format: PackFormat) -> Self {
+        let source = match format {
+            PackFormat::Markdown => MARKDOWN_TEMPLATE,
+            PackFormat::Xml => XML_TEMPLATE,
+        };
+        Template {
+            builtin: Some(format),
+            ..Self::parse(source).expect("built-in templates are valid")
+        }
+    }
+
+    /// Built-in template annotating each file with the commit that last changed it.
+    /// Custom templates are returned unchanged; they can use `{{last_change}}` directly.
+    pub fn with_history(self) -> Self {
+        let source = match self.builtin {
+            Some(PackFormat::Markdown) => MARKDOWN_HISTORY_TEMPLATE,
+            Some(PackFormat::Xml) => XML_HISTORY_TEMPLATE,
+            None => return self,
+        };
+        Template {
+            builtin: self.builtin,
+            ..Self::parse(source).expect("built-in templates are valid")
+        }
+    }
+
+    /// Renders the template for `files` into `out`. `{{last_change}}` is empty without `history`.
+    pub fn render<W: Write>(
+        &self,
+        files: &[FileEntry],
+        history: Option<&GitHistory>,
+        out: &mut W,
+    ) -> Result<()> {
+        let tokens: Vec<usize> = files.iter().map(|f| estimate_tokens(&f.content)).collect();
+        let file_count = files.len().to_string();
+        let total_tokens = tokens.iter().sum::<usize>().to_string();
+
+        let global = |name: &str| match name {
+            "file_count" => Some(file_count.clone()),
+            "total_tokens" => Some(total_tokens.clone()),
+            _ => None,
+        };
+
+        // The built-in XML format escapes attributes and keeps content in CDATA
+        // so any path or file content yields well-formed XML.
+        let xml = self.builtin == Some(PackFormat::Xml);
+        let attribute = |value: String| if xml { xml_attribute(&value) } else { value };
+
+        write_segments(out, &self.header, |name| global(name))?;
+
+        for (index, file) in files.iter().enumerate() {
+            write_segments(out, &self.file, |name| match name {
+                "path" => Some(attribute(file.relative_path.clone())),
+                "language" => Some(language_for(&file.path).to_string()),
+                "content" if xml => Some(xml_cdata(&file.content)),
+                "content" => Some(file.content.clone()),
+                "index" => Some((index + 1).to_string()),
+                "tokens" => Some(tokens[index].to_string()),
+                "last_change" => Some(attribute(
+                    history
+                        .map(|h| h.last_change(&file.relative_path))
+                        .unwrap_or_default(),
+                )),
+                _ => global(name),
+            })?;
+        }
+
+        write_segments(out, &self.footer, |name| global(name))
+    }
+}
+
+fn write_segments<W, F>(out: &mut W, segments: &[Segment], lookup: F) -> Result<()>
+where
+    W: Write,
+    F: Fn(&str) -> Option<String>,
+{
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => out.write_all(text.as_bytes())?,
+            Segment::Variable(name) => {
+                let Some(value) = lookup(name) else {
+                    bail!("Unknown template variable '{}'", name);
+                };
+                out.write_all(value.as_bytes())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files() -> Vec<FileEntry> {
+        vec![
+            FileEntry::fixture("src/main.rs", "fn main() {}"),
+            FileEntry::fixture("notes.txt", "hello"),
+        ]
+    }
+
+    fn render(template: &Template) -> String {
+        let mut out = Vec::new();
+        template.render(&files(), None, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_markdown_template_matches_legacy_output() {
+        assert_eq!(
+            render(&Template::builtin(PackFormat::Markdown)),
+            "src/main.rs\n```\nfn main() {}\n```\n\nnotes.txt\n```\nhello\n```\n\n"
+        );
+    }
+
+    #[test]
+    fn test_xml_template_escapes() {
+        let files = vec![FileEntry::fixture("a&\"b.xml", "<x/></file>]]>")];
+        let mut out = Vec::new();
+        Template::builtin(PackFormat::Xml)
+            .with_history()
+            .render(&files, None, &mut out)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<files>\n<file path=\"a&amp;&quot;b.xml\" language=\"XML\" last_change=\"\">\n<![CDATA[<x/></file>]]]]><![CDATA[>]]>\n</file>\n</files>\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_template_variables() {
+        let template = Template::parse(
+            "Files: {{file_count}}\n{{#files}}[{{index}}] {{path}} ({{language}}, {{tokens}})\n{{/files}}end",
+        )
+        .unwrap();
+        assert_eq!(
+            render(&template),
+            "Files: 2\n[1] src/main.rs (Rust, 3)\n[2] notes.txt (Text, 2)\nend"
+        );
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(Template::parse("{{path}}").is_err());
+        assert!(Template::parse("{{path}}{{#files}}{{/files}}").is_err());
+        assert!(Template::parse("{{#files}}{{unknown}}{{/files}}").is_err());
+        assert!(Template::from_sections("", "{{content", "").is_err());
+    }
+}
@@ -0,0 +1,771 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+
+use super::{Access, Result, Tool, ToolContext, diff, object_schema};
+use crate::models::{AppError, ToolSchema};
+
+/// Context lines a hunk may drop at each end when it does not match as given.
+const MAX_FUZZ: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Context,
+    Remove,
+    Add,
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    header: String,
+    /// 1-based start line in the old file, used as a hint for where to look.
+    old_start: usize,
+    lines: Vec<(LineKind, String)>,
+    old_no_newline: bool,
+    new_no_newline: bool,
+}
+
+/// The changes to one file. `old_path` is `None` for a new file and
+/// `new_path` is `None` for a deleted one.
+#[derive(Debug, Clone, Default)]
+struct FilePatch {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    hunks: Vec<Hunk>,
+    /// Started by a `diff --git` line, whose paths the `---`/`+++` lines refine.
+    git_header: bool,
+}
+
+impl FilePatch {
+    fn paths(&self) -> impl Iterator<Item = &String> {
+        self.old_path.iter().chain(self.new_path.iter())
+    }
+}
+
+/// Strips `a/` or `b/` and any trailing timestamp; `/dev/null` means no file.
+fn header_path(raw: &str, prefix: &str) -> Option<String> {
+    let path = raw.split('\t').next().unwrap_or(raw).trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+fn is_file_header(lines: &[&str], i: usize) -> bool {
+    lines[i].starts_with("--- ") && lines.get(i + 1).is_some_and(|l| l.starts_with("+++ "))
+}
+
+/// Parses `l,c` (or just `l`, meaning one line) into start and count.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+    Some((start.parse().ok()?, count.parse().ok()?))
+}
+
+/// Parses `@@ -l,c +l,c @@`, returning the old start line and, when both
+/// ranges are well formed, the old and new line counts.
+fn parse_hunk_header(line: &str) -> Result<(usize, Option<(usize, usize)>)> {
+    let start = line
+        .strip_prefix("@@ -")
+        .and_then(|rest| rest.split([',', ' ']).next())
+        .and_then(|start| start.parse().ok())
+        .ok_or_else(|| AppError(format!("Invalid hunk header: {}", line)))?;
+    let mut ranges = line.split(' ').skip(1);
+    let old = ranges.next().and_then(|r| r.strip_prefix('-'));
+    let new = ranges.next().and_then(|r| r.strip_prefix('+'));
+    let counts = match (old.and_then(parse_range), new.and_then(parse_range)) {
+        (Some((_, old)), Some((_, new))) => Some((old, new)),
+        _ => None,
+    };
+    Ok((start, counts))
+}
+
+fn parse_patch(text: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            let (old, new) = rest.rsplit_once(" b/").unwrap_or((rest, rest));
+            patches.push(FilePatch {
+                old_path: header_path(old, "a/"),
+                new_path: Some(new.to_string()),
+                git_header: true,
+                ..FilePatch::default()
+            });
+        } else if is_file_header(&lines, i) {
+            let old_path = header_path(&line[4..], "a/");
+            let new_path = header_path(&lines[i + 1][4..], "b/");
+            match patches.last_mut() {
+                Some(patch)
+                    if patch.git_header
+                        && patch.hunks.is_empty()
+                        && (old_path.is_none() || old_path == patch.old_path)
+                        && (new_path.is_none() || new_path == patch.new_path) =>
+                {
+                    patch.old_path = old_path;
+                    patch.new_path = new_path;
+                    patch.git_header = false;
+                }
+                _ => patches.push(FilePatch {
+                    old_path,
+                    new_path,
+                    ..FilePatch::default()
+                }),
+            }
+            i += 1;
+        } else if let Some(patch) = patches.last_mut().filter(|p| p.hunks.is_empty()) {
+            if let Some(from) = line.strip_prefix("rename from ") {
+                patch.old_path = Some(from.to_string());
+            } else if let Some(to) = line.strip_prefix("rename to ") {
+                patch.new_path = Some(to.to_string());
+            } else if line.starts_with("new file mode") {
+                patch.old_path = None;
+            } else if line.starts_with("deleted file mode") {
+                patch.new_path = None;
+            }
+        }
+
+        if line.starts_with("@@") {
+            let patch = patches
+                .last_mut()
+                .ok_or_else(|| AppError("Hunk found before any file header".to_string()))?;
+            let (old_start, counts) = parse_hunk_header(line)?;
+            let mut hunk = Hunk {
+                header: line.to_string(),
+                old_start,
+                lines: Vec::new(),
+                old_no_newline: false,
+                new_no_newline: false,
+            };
+            // Lines the header says are still to come. Until they are read, a
+            // `--- x`/`+++ y` pair is a removed and an added line, not a file
+            // header; after that (or without counts) hunks end at the next header.
+            let (mut old_left, mut new_left) = counts.unwrap_or((0, 0));
+            // Blank lines are usually context whose leading space got lost;
+            // trailing ones are just padding.
+            let mut blank_run = 0;
+            while i + 1 < lines.len()
+                && !lines[i + 1].starts_with("@@")
+                && !lines[i + 1].starts_with("diff --git ")
+                && (old_left > 0 || new_left > 0 || !is_file_header(&lines, i + 1))
+            {
+                let body = lines[i + 1];
+                let (kind, text) = match body.chars().next() {
+                    Some(' ') => (LineKind::Context, &body[1..]),
+                    Some('-') => (LineKind::Remove, &body[1..]),
+                    Some('+') => (LineKind::Add, &body[1..]),
+                    Some('\\') => {
+                        i += 1;
+                        match hunk.lines.last() {
+                            Some((LineKind::Remove, _)) => hunk.old_no_newline = true,
+                            Some((LineKind::Add, _)) => hunk.new_no_newline = true,
+                            _ => {
+                                hunk.old_no_newline = true;
+                                hunk.new_no_newline = true;
+                            }
+                        }
+                        continue;
+                    }
+                    None => {
+                        i += 1;
+                        blank_run += 1;
+                        old_left = old_left.saturating_sub(1);
+                        new_left = new_left.saturating_sub(1);
+                        hunk.lines.push((LineKind::Context, String::new()));
+                        continue;
+                    }
+                    Some(_) => break,
+                };
+                i += 1;
+                blank_run = 0;
+                if kind != LineKind::Add {
+                    old_left = old_left.saturating_sub(1);
+                }
+                if kind != LineKind::Remove {
+                    new_left = new_left.saturating_sub(1);
+                }
+                hunk.lines.push((kind, text.to_string()));
+            }
+            hunk.lines.truncate(hunk.lines.len() - blank_run);
+            patch.hunks.push(hunk);
+        }
+        i += 1;
+    }
+
+    Ok(patches)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Match {
+    Exact,
+    IgnoreTrailingSpace,
+    IgnoreSpace,
+}
+
+impl Match {
+    fn eq(self, a: &str, b: &str) -> bool {
+        match self {
+            Match::Exact => a == b,
+            Match::IgnoreTrailingSpace => a.trim_end() == b.trim_end(),
+            Match::IgnoreSpace => a.split_whitespace().eq(b.split_whitespace()),
+        }
+    }
+}
+
+/// Finds `old` in `file[from..]`, trying positions nearest `hint` first.
+fn find(file: &[String], old: &[&str], hint: usize, from: usize, mode: Match) -> Option<usize> {
+    if old.is_empty() {
+        return Some(hint.clamp(from, file.len()));
+    }
+    let last = file.len().checked_sub(old.len())?;
+    if from > last {
+        return None;
+    }
+    let hint = hint.clamp(from, last);
+    let fits = |at: usize| {
+        old.iter()
+            .enumerate()
+            .all(|(j, l)| mode.eq(&file[at + j], l))
+    };
+
+    (0..=(last - from)).find_map(|distance| {
+        [hint.checked_add(distance), hint.checked_sub(distance)]
+            .into_iter()
+            .flatten()
+            .filter(|&at| at >= from && at <= last)
+            .find(|&at| fits(at))
+    })
+}
+
+/// A file's lines plus what is needed to write them back unchanged.
+struct Text {
+    lines: Vec<String>,
+    trailing_newline: bool,
+    eol: &'static str,
+}
+
+impl Text {
+    fn parse(content: &str) -> Self {
+        Text {
+            lines: content.lines().map(str::to_string).collect(),
+            trailing_newline: content.is_empty() || content.ends_with('\n'),
+            eol: if content.contains("\r\n") {
+                "\r\n"
+            } else {
+                "\n"
+            },
+        }
+    }
+
+    fn render(&self) -> String {
+        let mut out = self.lines.join(self.eol);
+        if self.trailing_newline && !self.lines.is_empty() {
+            out.push_str(self.eol);
+        }
+        out
+    }
+}
+
+/// Applies `hunks` to `content`, returning the new content (when every hunk
+/// applied) and a report line per hunk.
+fn apply_hunks(content: &str, hunks: &[Hunk]) -> (Option<String>, Vec<String>) {
+    let mut text = Text::parse(content);
+    let mut reports = Vec::new();
+    let mut failed = false;
+    let mut from = 0;
+    let mut delta: isize = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let hint = (hunk.old_start.saturating_sub(1) as isize + delta).max(0) as usize;
+        let label = format!("  hunk {} ({})", index + 1, hunk.header);
+
+        let found = (0..=MAX_FUZZ).find_map(|fuzz| {
+            let lines = trim_context(&hunk.lines, fuzz)?;
+            let old: Vec<&str> = lines
+                .iter()
+                .filter(|(kind, _)| *kind != LineKind::Add)
+                .map(|(_, l)| l.as_str())
+                .collect();
+            [Match::Exact, Match::IgnoreTrailingSpace, Match::IgnoreSpace]
+                .into_iter()
+                .find_map(|mode| {
+                    find(&text.lines, &old, hint, from, mode)
+                        .map(|at| (at, fuzz, mode, lines, old.len()))
+                })
+        });
+
+        let Some((at, fuzz, mode, lines, old_len)) = found else {
+            failed = true;
+            reports.push(format!(
+                "{}: FAILED, context not found near line {}",
+                label,
+                hint + 1
+            ));
+            continue;
+        };
+
+        // Keep the file's own context lines, which may differ in whitespace.
+        let mut replacement = Vec::new();
+        let mut offset = at;
+        for (kind, line) in lines {
+            match kind {
+                LineKind::Context => {
+                    replacement.push(text.lines[offset].clone());
+                    offset += 1;
+                }
+                LineKind::Remove => offset += 1,
+                LineKind::Add => replacement.push(line.clone()),
+            }
+        }
+
+        let touches_end = at + old_len == text.lines.len();
+        let added = replacement.len();
+        text.lines.splice(at..at + old_len, replacement);
+        if touches_end {
+            if hunk.new_no_newline {
+                text.trailing_newline = false;
+            } else if hunk.old_no_newline {
+                text.trailing_newline = true;
+            }
+        }
+
+        let mut notes = Vec::new();
+        let shift = at as isize - hint as isize;
+        if shift != 0 {
+            notes.push(format!("offset {:+}", shift));
+        }
+        if mode != Match::Exact {
+            notes.push("ignoring whitespace".to_string());
+        }
+        if fuzz > 0 {
+            notes.push(format!("fuzz {}", fuzz));
+        }
+        reports.push(format!(
+            "{}: applied at line {}{}",
+            label,
+            at + 1,
+            if notes.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", notes.join(", "))
+            }
+        ));
+
+        from = at + added;
+        delta += added as isize - old_len as isize;
+    }
+
+    ((!failed).then(|| text.render()), reports)
+}
+
+/// Drops up to `fuzz` context lines from each end of a hunk, or `None` when
+/// there are not that many to drop.
+fn trim_context(lines: &[(LineKind, String)], fuzz: usize) -> Option<&[(LineKind, String)]> {
+    if fuzz == 0 {
+        return Some(lines);
+    }
+    let leading = lines
+        .iter()
+        .take_while(|(kind, _)| *kind == LineKind::Context)
+        .count();
+    let trailing = lines
+        .iter()
+        .rev()
+        .take_while(|(kind, _)| *kind == LineKind::Context)
+        .count();
+    if leading < fuzz && trailing < fuzz {
+        return None;
+    }
+    let start = leading.min(fuzz);
+    let end = lines.len() - trailing.min(fuzz);
+    (start < end).then(|| &lines[start..end])
+}
+
+/// A file write or deletion computed before anything touches the disk.
+struct Change {
+    display: String,
+    path: PathBuf,
+    old: Option<String>,
+    /// `None` deletes the file.
+    new: Option<String>,
+}
+
+/// The result of checking a whole patch against the workspace.
+struct Plan {
+    changes: Vec<Change>,
+    report: Vec<String>,
+    ok: bool,
+}
+
+fn read(path: &PathBuf, display: &str) -> Result<String> {
+    fs::read_to_string(path)
+        .map_err(|e| AppError(format!("Failed to read file '{}': {}", display, e)))
+}
+
+fn plan(ctx: &ToolContext, patches: &[FilePatch]) -> Result<Plan> {
+    let mut plan = Plan {
+        changes: Vec::new(),
+        report: Vec::new(),
+        ok: true,
+    };
+
+    for patch in patches {
+        match (&patch.old_path, &patch.new_path) {
+            (None, None) => {
+                return Err(AppError(
+                    "Patch has a file section where both paths are /dev/null".to_string(),
+                ));
+            }
+            (None, Some(new_path)) => {
+                let path = ctx.resolve(new_path, Access::Write)?;
+                if path.exists() && !read(&path, new_path)?.is_empty() {
+                    plan.ok = false;
+                    plan.report
+                        .push(format!("A {}: FAILED, file already exists", new_path));
+                    continue;
+                }
+                let (content, hunks) = apply_hunks("", &patch.hunks);
+                plan.ok &= content.is_some();
+                plan.report.push(format!(
+                    "A {} (+{} lines)",
+                    new_path,
+                    content.as_deref().unwrap_or("").lines().count()
+                ));
+                plan.report.extend(hunks);
+                plan.changes.push(Change {
+                    display: new_path.clone(),
+                    path,
+                    old: None,
+                    new: content,
+                });
+            }
+            (Some(old_path), None) => {
+                let path = ctx.resolve(old_path, Access::Write)?;
+                if !path.is_file() {
+                    plan.ok = false;
+                    plan.report
+                        .push(format!("D {}: FAILED, file does not exist", old_path));
+                    continue;
+                }
+                // The removed lines must match the file, so a stale patch cannot
+                // delete a file that has changed since.
+                let old = read(&path, old_path)?;
+                let (content, hunks) = apply_hunks(&old, &patch.hunks);
+                match content.as_deref() {
+                    Some("") => plan.report.push(format!("D {}", old_path)),
+                    Some(_) => {
+                        plan.ok = false;
+                        plan.report.push(format!(
+                            "D {}: FAILED, the patch does not remove the whole file",
+                            old_path
+                        ));
+                    }
+                    None => {
+                        plan.ok = false;
+                        plan.report.push(format!("D {}: FAILED", old_path));
+                    }
+                }
+                plan.report.extend(hunks);
+                plan.changes.push(Change {
+                    display: old_path.clone(),
+                    old: Some(old),
+                    path,
+                    new: None,
+                });
+            }
+            (Some(old_path), Some(new_path)) => {
+                let path = ctx.resolve(old_path, Access::Write)?;
+                if !path.is_file() {
+                    plan.ok = false;
+                    let label = if old_path == new_path { "M" } else { "R" };
+                    plan.report.push(format!(
+                        "{} {}: FAILED, file does not exist",
+                        label, old_path
+                    ));
+                    continue;
+                }
+                let old = read(&path, old_path)?;
+                let (content, hunks) = apply_hunks(&old, &patch.hunks);
+                plan.ok &= content.is_some();
+                let new = content.unwrap_or_else(|| old.clone());
+
+                if old_path == new_path {
+                    plan.report
+                        .push(format!("M {}", diff::summary(new_path, Some(&old), &new)));
+                    plan.report.extend(hunks);
+                    plan.changes.push(Change {
+                        display: new_path.clone(),
+                        path,
+                        old: Some(old),
+                        new: Some(new),
+                    });
+                    continue;
+                }
+
+                let target = ctx.resolve(new_path, Access::Write)?;
+                if target.exists() {
+                    plan.ok = false;
+                    plan.report.push(format!(
+                        "R {} -> {}: FAILED, target already exists",
+                        old_path, new_path
+                    ));
+                    continue;
+                }
+                let stats = diff::stats(Some(&old), &new);
+                plan.report.push(format!(
+                    "R {} -> {} (+{} -{})",
+                    old_path, new_path, stats.added, stats.removed
+                ));
+                plan.report.extend(hunks);
+                plan.changes.push(Change {
+                    display: new_path.clone(),
+                    path: target,
+                    old: Some(old.clone()),
+                    new: Some(new),
+                });
+                plan.changes.push(Change {
+                    display: old_path.clone(),
+                    path,
+                    old: Some(old),
+                    new: None,
+                });
+            }
+        }
+    }
+    Ok(plan)
+}
+
+fn parse_input(input: &Value) -> Result<Vec<FilePatch>> {
+    let text = input
+        .get("patch")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| {
+            AppError("Missing or empty required 'patch' parameter for apply_patch".to_string())
+        })?;
+    let patches = parse_patch(text)?;
+    if patches.is_empty() {
+        return Err(AppError(
+            "No file headers ('--- a/path' / '+++ b/path') found in patch".to_string(),
+        ));
+    }
+    Ok(patches)
+}
+
+/// Applies a multi-file unified diff, checking every hunk before writing anything.
+pub struct ApplyPatchTool;
+
+#[async_trait]
+impl Tool for ApplyPatchTool {
+    fn name(&self) -> &str {
+        "apply_patch"
+    }
+
+    fn description(&self) -> &str {
+        "Apply a unified diff that may create, modify, delete or rename several files. Use '--- a/path' and '+++ b/path' headers ('/dev/null' for created or deleted files) followed by '@@' hunks. Hunks are located near their line numbers and tolerate shifted lines and whitespace differences. Nothing is written unless every hunk applies; the result reports each hunk, and after a failure the whole patch must be sent again."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[(
+                "patch",
+                "string",
+                "The unified diff to apply, covering one or more files.",
+            )],
+            &["patch"],
+        )
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn paths(&self, input: &Value) -> Vec<String> {
+        parse_input(input)
+            .map(|patches| patches.iter().flat_map(|p| p.paths().cloned()).collect())
+            .unwrap_or_default()
+    }
+
+    fn preview(&self, ctx: &ToolContext, input: &Value) -> String {
+        let plan = match parse_input(input).and_then(|patches| plan(ctx, &patches)) {
+            Ok(plan) => plan,
+            Err(e) => return format!("The patch will fail: {}", e),
+        };
+        if !plan.ok {
+            return format!("The patch will fail:\n{}", plan.report.join("\n"));
+        }
+        plan.changes
+            .iter()
+            .map(|change| match &change.new {
+                Some(new) => diff::colored(&change.display, change.old.as_deref(), new),
+                None => format!("\x1b[1mdelete {}\x1b[0m\n", change.display),
+            })
+            .collect()
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let plan = plan(ctx, &parse_input(&input)?)?;
+        if !plan.ok {
+            return Err(AppError(format!(
+                "Patch did not apply; no files were changed, including those whose hunks matched. Fix the failed hunks and resend the whole patch.\n{}",
+                plan.report.join("\n")
+            )));
+        }
+
+        for change in plan.changes.iter().filter(|c| c.new.is_some()) {
+            if let Some(parent) = change.path.parent() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    AppError(format!(
+                        "Failed to create directory '{}': {}",
+                        parent.display(),
+                        e
+                    ))
+                })?;
+            }
+            fs::write(&change.path, change.new.as_deref().unwrap_or("")).map_err(|e| {
+                AppError(format!("Failed to write file '{}': {}", change.display, e))
+            })?;
+        }
+        for change in plan.changes.iter().filter(|c| c.new.is_none()) {
+            fs::remove_file(&change.path).map_err(|e| {
+                AppError(format!("Failed to delete file '{}': {}", change.display, e))
+            })?;
+        }
+
+        Ok(format!("Patch applied.\n{}", plan.report.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Workspace;
+    use serde_json::json;
+
+    const PATCH: &str = "\
+diff --git a/old.txt b/renamed.txt
+similarity index 100%
+rename from old.txt
+rename to renamed.txt
+--- a/lib.rs
++++ b/lib.rs
+@@ -1,3 +1,3 @@
+ fn a() {}
+-fn b() {}
++fn b() { 1 }
+ fn c() {}
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1,2 @@
++fn new() {}
++fn newer() {}
+--- a/gone.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn gone() {}
+";
+
+    #[tokio::test]
+    async fn test_apply_multi_file_patch() {
+        let dir = tempfile::tempdir().unwrap();
+        // The hunk's context sits three lines lower and with different indentation.
+        fs::write(
+            dir.path().join("lib.rs"),
+            "// a\n// b\n// c\n  fn a() {}\nfn b() {}\nfn c() {}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("old.txt"), "keep\n").unwrap();
+        fs::write(dir.path().join("gone.rs"), "fn gone() {}\n").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        let report = ApplyPatchTool
+            .call(&ctx, json!({"patch": PATCH}))
+            .await
+            .unwrap();
+        assert!(
+            report.contains("applied at line 4 (offset +3, ignoring whitespace)"),
+            "{}",
+            report
+        );
+
+        let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+        assert_eq!(
+            read("lib.rs"),
+            "// a\n// b\n// c\n  fn a() {}\nfn b() { 1 }\nfn c() {}\n"
+        );
+        assert_eq!(read("new.rs"), "fn new() {}\nfn newer() {}\n");
+        assert_eq!(read("renamed.txt"), "keep\n");
+        assert!(!dir.path().join("old.txt").exists());
+        assert!(!dir.path().join("gone.rs").exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_hunk_changes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("lib.rs"), "fn a() {}\nfn x() {}\n").unwrap();
+        fs::write(dir.path().join("gone.rs"), "fn gone() {}\n").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        let error = ApplyPatchTool
+            .call(&ctx, json!({"patch": PATCH}))
+            .await
+            .unwrap_err();
+        assert!(
+            error.0.contains("hunk 1 (@@ -1,3 +1,3 @@): FAILED"),
+            "{}",
+            error
+        );
+        assert!(error.0.contains("resend the whole patch"), "{}", error);
+        assert!(dir.path().join("gone.rs").exists());
+        assert!(!dir.path().join("new.rs").exists());
+
+        // A deletion whose removed lines no longer match leaves the file alone.
+        fs::write(
+            dir.path().join("gone.rs"),
+            "fn gone() {}
+fn added() {}
+",
+        )
+        .unwrap();
+        let delete = "--- a/gone.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-fn gone() {}\n";
+        let error = ApplyPatchTool
+            .call(&ctx, json!({"patch": delete}))
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .0
+                .contains("D gone.rs: FAILED, the patch does not remove the whole file"),
+            "{}",
+            error
+        );
+        assert!(dir.path().join("gone.rs").exists());
+    }
+
+    #[tokio::test]
+    async fn test_hunk_lines_that_look_like_file_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("schema.sql"), "-- x\nSELECT 1;\n").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        // Removing a SQL comment and adding a line starting with "++".
+        let patch =
+            "--- a/schema.sql\n+++ b/schema.sql\n@@ -1,2 +1,2 @@\n--- x\n+++ y\n SELECT 1;\n";
+        let patches = parse_patch(patch).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].hunks[0].lines.len(), 3);
+
+        ApplyPatchTool
+            .call(&ctx, json!({"patch": patch}))
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("schema.sql")).unwrap(),
+            "++ y\nSELECT 1;\n"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{Access, Result, ToolContext, diff};
+use crate::models::AppError;
+
+/// The contents of the files a turn changed, taken before its first change to each.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub id: usize,
+    /// The user message that started the turn.
+    pub label: String,
+    pub created: DateTime<Local>,
+    /// Turn that created it.
+    turn: usize,
+    /// Paths relative to the workspace root; `None` if the file did not exist.
+    files: BTreeMap<PathBuf, Option<Vec<u8>>>,
+}
+
+impl Checkpoint {
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(|p| p.as_path())
+    }
+}
+
+/// Per-turn snapshots of files about to be changed by mutating tools.
+#[derive(Debug, Default)]
+pub struct Checkpoints {
+    checkpoints: Vec<Checkpoint>,
+    /// Label for the checkpoint the current turn creates on its first change.
+    pending: Option<String>,
+    next_id: usize,
+    turn: usize,
+    /// Shell commands run, with their turn; their changes are not recorded.
+    commands: Vec<(usize, String)>,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a new turn; its first recorded change opens a new checkpoint.
+    pub fn start_turn(&mut self, label: &str) {
+        self.turn += 1;
+        self.pending = Some(label.to_string());
+    }
+
+    /// Notes a shell command; what it changes cannot be restored.
+    pub fn record_command(&mut self, command: &str) {
+        self.commands.push((self.turn, command.to_string()));
+    }
+
+    /// Commands run in the turn of checkpoint `id` or later, which a restore
+    /// to it does not undo.
+    pub fn commands_since(&self, id: usize) -> Vec<&str> {
+        let Some(checkpoint) = self.checkpoints.iter().find(|c| c.id == id) else {
+            return Vec::new();
+        };
+        self.commands
+            .iter()
+            .filter(|(turn, _)| *turn >= checkpoint.turn)
+            .map(|(_, command)| command.as_str())
+            .collect()
+    }
+
+    /// Saves the current contents of `paths` unless this turn already has them.
+    pub fn record(&mut self, ctx: &ToolContext, paths: &[String]) -> Result<()> {
+        for path in paths {
+            // A path outside the sandbox fails in the tool itself.
+            let Ok(resolved) = ctx.resolve(path, Access::Write) else {
+                continue;
+            };
+            let relative = ctx.relative(&resolved).to_path_buf();
+
+            if let Some(label) = self.pending.take() {
+                self.next_id += 1;
+                self.checkpoints.push(Checkpoint {
+                    id: self.next_id,
+                    label,
+                    created: Local::now(),
+                    turn: self.turn,
+                    files: BTreeMap::new(),
+                });
+            }
+            let Some(checkpoint) = self.checkpoints.last_mut() else {
+                continue;
+            };
+            if let Entry::Vacant(entry) = checkpoint.files.entry(relative) {
+                let content = match fs::read(&resolved) {
+                    Ok(bytes) => Some(bytes),
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                    Err(e) => {
+                        return Err(AppError(format!(
+                            "Failed to checkpoint '{}': {}",
+                            entry.key().display(),
+                            e
+                        )));
+                    }
+                };
+                entry.insert(content);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    pub fn latest(&self) -> Option<usize> {
+        self.checkpoints.last().map(|c| c.id)
+    }
+
+    /// Every file changed since checkpoint `id`, with its contents from before that turn.
+    fn state_before(&self, id: usize) -> Result<BTreeMap<&Path, Option<&[u8]>>> {
+        if !self.checkpoints.iter().any(|c| c.id == id) {
+            return Err(AppError(format!("No checkpoint {}", id)));
+        }
+        let mut state = BTreeMap::new();
+        for checkpoint in self.checkpoints.iter().filter(|c| c.id >= id) {
+            for (path, content) in &checkpoint.files {
+                state
+                    .entry(path.as_path())
+                    .or_insert_with(|| content.as_deref());
+            }
+        }
+        Ok(state)
+    }
+
+    /// Colored diffs from the state before checkpoint `id` to the files on disk now.
+    pub fn diff(&self, ctx: &ToolContext, id: usize) -> Result<String> {
+        let mut out = String::new();
+        for (path, before) in self.state_before(id)? {
+            let display = path.to_string_lossy();
+            let before = before.map(String::from_utf8_lossy);
+            let now = fs::read(ctx.root().join(path))
+                .ok()
+                .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+
+            match (before, now) {
+                (Some(old), Some(new)) if old == new => {}
+                (None, None) => {}
+                (old, Some(new)) => out.push_str(&diff::colored(&display, old.as_deref(), &new)),
+                (Some(_), None) => out.push_str(&format!("\x1b[1mdeleted {}\x1b[0m\n", display)),
+            }
+        }
+        if out.is_empty() {
+            out.push_str("No changes since this checkpoint.\n");
+        }
+        Ok(out)
+    }
+
+    /// Puts every file changed since checkpoint `id` back to its earlier contents,
+    /// dropping that checkpoint and later ones. Returns the restored paths.
+    pub fn restore(&mut self, ctx: &ToolContext, id: usize) -> Result<Vec<PathBuf>> {
+        let mut restored = Vec::new();
+        for (path, content) in self.state_before(id)? {
+            let full = ctx.root().join(path);
+            let result = match content {
+                Some(bytes) => full
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(&full, bytes)),
+                None if full.exists() => fs::remove_file(&full),
+                None => Ok(()),
+            };
+            result
+                .map_err(|e| AppError(format!("Failed to restore '{}': {}", path.display(), e)))?;
+            restored.push(path.to_path_buf());
+        }
+        self.checkpoints.retain(|c| c.id < id);
+        Ok(restored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Workspace;
+
+    #[test]
+    fn test_restore_undoes_later_turns() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "one\n").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+        let mut checkpoints = Checkpoints::new();
+        let paths = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        checkpoints.start_turn("first");
+        checkpoints.record(&ctx, &paths(&["a.txt"])).unwrap();
+        fs::write(dir.path().join("a.txt"), "two\n").unwrap();
+        checkpoints.record(&ctx, &paths(&["a.txt"])).unwrap();
+        fs::write(dir.path().join("a.txt"), "three\n").unwrap();
+
+        checkpoints.start_turn("second");
+        checkpoints.record_command("cargo fmt");
+        checkpoints
+            .record(&ctx, &paths(&["a.txt", "b.txt"]))
+            .unwrap();
+        fs::write(dir.path().join("a.txt"), "four\n").unwrap();
+        fs::write(dir.path().join("b.txt"), "new\n").unwrap();
+
+        assert_eq!(checkpoints.list().len(), 2);
+        assert!(checkpoints.diff(&ctx, 2).unwrap().contains("+++ b/b.txt"));
+        assert_eq!(checkpoints.commands_since(1), vec!["cargo fmt"]);
+        assert_eq!(checkpoints.commands_since(2), vec!["cargo fmt"]);
+
+        let restored = checkpoints.restore(&ctx, 2).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "three\n"
+        );
+        assert!(!dir.path().join("b.txt").exists());
+
+        checkpoints.restore(&ctx, 1).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.txt")).unwrap(),
+            "one\n"
+        );
+        assert!(checkpoints.list().is_empty());
+    }
+}
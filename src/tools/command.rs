@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+
+use super::run_command::{RunCommandConfig, run_shell};
+use super::{Result, Tool, ToolContext, object_schema};
+use crate::models::ToolSchema;
+
+/// A `[[code.tools]]` entry in `.pai.toml`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CommandToolConfig {
+    pub name: String,
+    pub description: String,
+    /// Shell command run from the workspace root.
+    pub command: String,
+}
+
+/// A project-defined tool that runs a fixed shell command and reports its output.
+pub struct CommandTool {
+    name: String,
+    description: String,
+    command: String,
+    run: RunCommandConfig,
+}
+
+impl CommandTool {
+    /// `run` supplies the timeout and output limit shared with `run_command`.
+    pub fn from_config(config: &CommandToolConfig, run: &RunCommandConfig) -> Self {
+        CommandTool {
+            name: config.name.clone(),
+            description: config.description.clone(),
+            command: config.command.clone(),
+            run: run.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for CommandTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(&[], &[])
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn preview(&self, ctx: &ToolContext, _input: &Value) -> String {
+        format!("$ {}\n  (in {})", self.command, ctx.root().display())
+    }
+
+    fn command(&self, _input: &Value) -> Option<String> {
+        Some(self.command.clone())
+    }
+
+    async fn call(&self, ctx: &ToolContext, _input: Value) -> Result<String> {
+        run_shell(
+            ctx.root(),
+            &self.command,
+            Duration::from_secs(self.run.timeout_secs.max(1)),
+            self.run.max_output_bytes,
+        )
+        .await
+    }
+}
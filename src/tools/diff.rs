@@ -0,0 +1,344 @@
+//! Line diffs of file edits: colored previews for approval prompts and
+//! compact summaries for tool results.
+
+use similar::{ChangeTag, DiffOp, TextDiff};
+use std::path::Path;
+
+use crate::md::language_for;
+
+/// Unchanged lines shown around each hunk.
+const CONTEXT_LINES: usize = 3;
+/// Diff lines shown in a preview before the rest is elided.
+const MAX_PREVIEW_LINES: usize = 200;
+
+const RESET: &str = "\x1b[0m";
+const ADDED: &str = "\x1b[48;5;22m";
+const REMOVED: &str = "\x1b[48;5;52m";
+const HUNK: &str = "\x1b[36m";
+const BOLD: &str = "\x1b[1m";
+const KEYWORD: &str = "\x1b[95m";
+const STRING: &str = "\x1b[93m";
+const COMMENT: &str = "\x1b[90m";
+
+/// Line counts of an edit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub added: usize,
+    pub removed: usize,
+    pub hunks: usize,
+}
+
+fn text_diff<'a>(old: &'a str, new: &'a str) -> TextDiff<'a, 'a, 'a, str> {
+    TextDiff::from_lines(old, new)
+}
+
+fn hunks(diff: &TextDiff<'_, '_, '_, str>) -> Vec<Vec<DiffOp>> {
+    diff.grouped_ops(CONTEXT_LINES)
+        .into_iter()
+        .filter(|group| group.iter().any(|op| op.tag() != similar::DiffTag::Equal))
+        .collect()
+}
+
+/// Counts added and removed lines; `old` is `None` for a new file.
+pub fn stats(old: Option<&str>, new: &str) -> DiffStats {
+    let diff = text_diff(old.unwrap_or(""), new);
+    let mut stats = DiffStats {
+        hunks: hunks(&diff).len(),
+        ..DiffStats::default()
+    };
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => stats.added += 1,
+            ChangeTag::Delete => stats.removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    stats
+}
+
+/// One-line description of an edit for the tool result, e.g.
+/// `src/lib.rs: +3 -1 in 2 hunks (lines 10-14, 40)`.
+pub fn summary(path: &str, old: Option<&str>, new: &str) -> String {
+    let Some(old) = old else {
+        return format!("created {} (+{} lines)", path, new.lines().count());
+    };
+
+    let diff = text_diff(old, new);
+    let groups = hunks(&diff);
+    if groups.is_empty() {
+        return format!("{}: no changes", path);
+    }
+
+    let stats = stats(Some(old), new);
+    let ranges: Vec<String> = groups
+        .iter()
+        .map(|group| {
+            let start = group.first().map_or(0, |op| op.new_range().start) + 1;
+            let end = group.last().map_or(0, |op| op.new_range().end);
+            if end <= start {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect();
+    format!(
+        "{}: +{} -{} in {} hunk{} (lines {})",
+        path,
+        stats.added,
+        stats.removed,
+        stats.hunks,
+        if stats.hunks == 1 { "" } else { "s" },
+        ranges.join(", ")
+    )
+}
+
+/// Unified diff with ANSI colors and light syntax highlighting, for terminals.
+pub fn colored(path: &str, old: Option<&str>, new: &str) -> String {
+    let syntax = Syntax::for_path(Path::new(path));
+    let diff = text_diff(old.unwrap_or(""), new);
+    let stats = stats(old, new);
+
+    let mut out = format!(
+        "{BOLD}--- {}{RESET}\n{BOLD}+++ b/{}{RESET}\n",
+        if old.is_some() {
+            format!("a/{}", path)
+        } else {
+            "/dev/null".to_string()
+        },
+        path
+    );
+    out.push_str(&format!(
+        "\x1b[32m+{}\x1b[0m \x1b[31m-{}\x1b[0m\n",
+        stats.added, stats.removed
+    ));
+
+    let mut shown = 0;
+    let total: usize = hunks(&diff)
+        .iter()
+        .flat_map(|group| group.iter())
+        .map(|op| diff.iter_changes(op).count())
+        .sum();
+
+    for group in hunks(&diff) {
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        let old_range = first.old_range().start..last.old_range().end;
+        let new_range = first.new_range().start..last.new_range().end;
+        out.push_str(&format!(
+            "{HUNK}@@ -{},{} +{},{} @@{RESET}\n",
+            old_range.start + 1,
+            old_range.len(),
+            new_range.start + 1,
+            new_range.len()
+        ));
+
+        for op in &group {
+            for change in diff.iter_changes(op) {
+                if shown == MAX_PREVIEW_LINES {
+                    out.push_str(&format!("... {} more diff lines\n", total - shown));
+                    return out;
+                }
+                shown += 1;
+
+                let (marker, background) = match change.tag() {
+                    ChangeTag::Insert => ('+', ADDED),
+                    ChangeTag::Delete => ('-', REMOVED),
+                    ChangeTag::Equal => (' ', ""),
+                };
+                let line = change.value().trim_end_matches(['\n', '\r']);
+                out.push_str(background);
+                out.push(marker);
+                out.push_str(&syntax.highlight(line, background));
+                out.push_str(RESET);
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Just enough of a language's lexical structure to color keywords, strings and comments.
+struct Syntax {
+    line_comment: Option<&'static str>,
+    /// Whether `'` starts a string (false where it marks chars or lifetimes).
+    single_quote_strings: bool,
+    keywords: &'static [&'static str],
+}
+
+impl Syntax {
+    fn for_path(path: &Path) -> Self {
+        let (line_comment, single_quote_strings, keywords): (_, _, &[&str]) =
+            match language_for(path) {
+                "Rust" => (
+                    Some("//"),
+                    false,
+                    &[
+                        "as", "async", "await", "const", "crate", "else", "enum", "fn", "for",
+                        "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+                        "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+                        "type", "unsafe", "use", "where", "while",
+                    ],
+                ),
+                "Python" => (
+                    Some("#"),
+                    true,
+                    &[
+                        "and", "as", "async", "await", "class", "def", "elif", "else", "except",
+                        "finally", "for", "from", "if", "import", "in", "is", "lambda", "not",
+                        "or", "pass", "raise", "return", "try", "while", "with", "yield",
+                    ],
+                ),
+                "Shell" | "Ruby" | "TOML" | "YAML" => (
+                    Some("#"),
+                    true,
+                    &[
+                        "case", "do", "done", "elif", "else", "end", "esac", "fi", "for",
+                        "function", "if", "in", "then", "while",
+                    ],
+                ),
+                "JavaScript" | "TypeScript" | "Go" | "Java" | "Kotlin" | "C" | "C++" | "C#"
+                | "Swift" | "PHP" => (
+                    Some("//"),
+                    true,
+                    &[
+                        "break",
+                        "case",
+                        "class",
+                        "const",
+                        "continue",
+                        "else",
+                        "export",
+                        "extends",
+                        "for",
+                        "func",
+                        "function",
+                        "if",
+                        "import",
+                        "interface",
+                        "let",
+                        "new",
+                        "package",
+                        "private",
+                        "public",
+                        "return",
+                        "static",
+                        "struct",
+                        "switch",
+                        "this",
+                        "type",
+                        "var",
+                        "void",
+                        "while",
+                    ],
+                ),
+                _ => (None, false, &[]),
+            };
+        Syntax {
+            line_comment,
+            single_quote_strings,
+            keywords,
+        }
+    }
+
+    /// Colors tokens in `line`, restoring `background` after each one.
+    fn highlight(&self, line: &str, background: &str) -> String {
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+        let restore = |out: &mut String| {
+            out.push_str(RESET);
+            out.push_str(background);
+        };
+
+        while let Some(c) = rest.chars().next() {
+            if let Some(comment) = self.line_comment
+                && rest.starts_with(comment)
+            {
+                out.push_str(COMMENT);
+                out.push_str(rest);
+                restore(&mut out);
+                break;
+            }
+
+            if c == '"' || (c == '\'' && self.single_quote_strings) {
+                let end = string_end(rest, c);
+                out.push_str(STRING);
+                out.push_str(&rest[..end]);
+                restore(&mut out);
+                rest = &rest[end..];
+                continue;
+            }
+
+            if c.is_alphabetic() || c == '_' {
+                let end = rest
+                    .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                    .unwrap_or(rest.len());
+                let word = &rest[..end];
+                if self.keywords.contains(&word) {
+                    out.push_str(KEYWORD);
+                    out.push_str(word);
+                    restore(&mut out);
+                } else {
+                    out.push_str(word);
+                }
+                rest = &rest[end..];
+                continue;
+            }
+
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        out
+    }
+}
+
+/// Byte length of the string literal at the start of `text`, including its quotes.
+fn string_end(text: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_and_stats() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+
+        assert_eq!(
+            stats(Some(old), new),
+            DiffStats {
+                added: 2,
+                removed: 1,
+                hunks: 2
+            }
+        );
+        assert_eq!(
+            summary("x.txt", Some(old), new),
+            "x.txt: +2 -1 in 2 hunks (lines 1-5, 8-11)"
+        );
+        assert_eq!(summary("x.txt", Some(old), old), "x.txt: no changes");
+        assert_eq!(summary("x.txt", None, new), "created x.txt (+11 lines)");
+    }
+
+    #[test]
+    fn test_colored_diff_highlights_changes() {
+        let preview = colored("src/a.rs", Some("let a = 1;\n"), "let a = \"two\"; // x\n");
+
+        assert!(preview.contains("--- a/src/a.rs"));
+        assert!(preview.contains("@@ -1,1 +1,1 @@"));
+        assert!(preview.contains(&format!("{REMOVED}-{KEYWORD}let")));
+        assert!(preview.contains(&format!("{STRING}\"two\"")));
+        assert!(preview.contains(&format!("{COMMENT}// x")));
+    }
+}
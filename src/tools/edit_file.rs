@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs;
+
+use super::{Access, Result, Tool, ToolContext, diff, object_schema, optional_str, required_str};
+use crate::models::{AppError, ToolSchema};
+
+/// Writes or overwrites a whole file, creating parent directories as needed.
+pub struct EditFileTool;
+
+#[async_trait]
+impl Tool for EditFileTool {
+    fn name(&self) -> &str {
+        "edit_file"
+    }
+
+    fn description(&self) -> &str {
+        "Writes or overwrites a file with the provided content. If the file path doesn't exist, it (and any necessary parent directories) will be created. If the file exists, its content will be completely replaced."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[
+                (
+                    "path",
+                    "string",
+                    "The relative path to the file to write or create.",
+                ),
+                (
+                    "content",
+                    "string",
+                    "The full new content for the file. If the file exists, its entire content will be replaced. If it doesn't exist, it will be created with this content.",
+                ),
+            ],
+            &["path", "content"],
+        )
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn preview(&self, ctx: &ToolContext, input: &Value) -> String {
+        let path = optional_str(input, "path").unwrap_or("<missing path>");
+        let content = input.get("content").and_then(|v| v.as_str()).unwrap_or("");
+        let existing = ctx
+            .resolve(path, Access::Write)
+            .ok()
+            .and_then(|resolved| fs::read_to_string(resolved).ok());
+
+        diff::colored(path, existing.as_deref(), content)
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let path_str = required_str(&input, "path", "edit_file")?;
+
+        let content = input
+            .get("content")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| {
+                AppError("Missing required 'content' parameter for edit_file".to_string())
+            })?;
+
+        let path = ctx.resolve(path_str, Access::Write)?;
+        let existing = fs::read_to_string(&path).ok();
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            if !parent.exists() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    AppError(format!(
+                        "Failed to create directory '{}': {}",
+                        parent.display(),
+                        e
+                    ))
+                })?;
+            } else if !parent.is_dir() {
+                return Err(AppError(format!(
+                    "Cannot create directory because path '{}' exists and is not a directory.",
+                    parent.display()
+                )));
+            }
+        }
+
+        fs::write(&path, content).map_err(|e| {
+            AppError(format!(
+                "Failed to write to file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        Ok(format!(
+            "Successfully wrote content to {} ({})",
+            path_str,
+            diff::summary(path_str, existing.as_deref(), content)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ReadFileTool, Workspace};
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_edit_then_read_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        EditFileTool
+            .call(&ctx, json!({"path": "src/new.rs", "content": "fn a() {}"}))
+            .await
+            .unwrap();
+
+        let content = ReadFileTool
+            .call(&ctx, json!({"path": "src/new.rs"}))
+            .await
+            .unwrap();
+        assert_eq!(content, "     1\tfn a() {}");
+        assert!(EditFileTool.call(&ctx, json!({"path": "x"})).await.is_err());
+        assert!(
+            EditFileTool
+                .call(&ctx, json!({"path": "../outside.rs", "content": ""}))
+                .await
+                .is_err()
+        );
+    }
+}
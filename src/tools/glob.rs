@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use globset::GlobBuilder;
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use super::list_files::SortKey;
+use super::{Access, Result, Tool, ToolContext, object_schema, optional_str, required_str};
+use crate::models::{AppError, ToolSchema};
+
+/// Paths returned when the model does not pass a `limit`.
+const DEFAULT_LIMIT: usize = 200;
+
+/// Finds files whose path matches a glob pattern.
+pub struct GlobTool;
+
+#[async_trait]
+impl Tool for GlobTool {
+    fn name(&self) -> &str {
+        "glob"
+    }
+
+    fn description(&self) -> &str {
+        "Find files by path pattern, e.g. '**/*.rs' or 'src/**/test_*.py'. Patterns are matched against paths relative to the search directory. Returns one workspace-relative path per line, skipping the same paths as list_files."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[
+                (
+                    "pattern",
+                    "string",
+                    "Glob pattern to match, e.g. '**/*.rs'.",
+                ),
+                (
+                    "path",
+                    "string",
+                    "Optional directory to search in, relative to the workspace. Defaults to '.'.",
+                ),
+                (
+                    "sort",
+                    "string",
+                    "'name' (default), 'modified' (newest first) or 'size' (largest first).",
+                ),
+                (
+                    "limit",
+                    "integer",
+                    "Maximum number of paths to return (default 200).",
+                ),
+            ],
+            &["pattern"],
+        )
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let pattern = required_str(&input, "pattern", "glob")?;
+        // `*` stays within one path component; only `**` crosses directories.
+        let matcher = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| AppError(format!("Invalid glob '{}': {}", pattern, e)))?
+            .compile_matcher();
+        let sort = SortKey::parse(optional_str(&input, "sort"))?;
+        let limit = input
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|n| n as usize)
+            .filter(|&n| n > 0)
+            .unwrap_or(DEFAULT_LIMIT);
+        let base = ctx.resolve(optional_str(&input, "path").unwrap_or("."), Access::Read)?;
+
+        let mut found = Vec::new();
+        let walker = WalkDir::new(&base)
+            .into_iter()
+            .filter_entry(|entry| entry.path() == base || !ctx.is_skipped(entry.path()));
+        for entry in walker.filter_map(|e| e.ok()) {
+            let Ok(relative) = entry.path().strip_prefix(&base) else {
+                continue;
+            };
+            if entry.file_type().is_file()
+                && matcher.is_match(relative)
+                && ctx
+                    .workspace
+                    .check(ctx.relative(entry.path()), Access::Read)
+                    .is_ok()
+                && let Ok(meta) = entry.metadata()
+            {
+                found.push((entry.path().to_path_buf(), meta));
+            }
+        }
+
+        if found.is_empty() {
+            return Ok(format!("No files match '{}'.", pattern));
+        }
+        sort.sort(&mut found);
+
+        let total = found.len();
+        let mut out: Vec<String> = found
+            .iter()
+            .take(limit)
+            .map(|(path, _)| ctx.relative(path).to_string_lossy().to_string())
+            .collect();
+        if total > limit {
+            out.push(format!(
+                "... {} more files; narrow the pattern or raise limit",
+                total - limit
+            ));
+        }
+        Ok(out.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Workspace;
+    use serde_json::json;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_glob_matches_relative_to_path() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("src/a.rs"), "fn a() {}").unwrap();
+        fs::write(dir.path().join("src/nested/b.rs"), "").unwrap();
+        fs::write(dir.path().join("target/c.rs"), "").unwrap();
+        fs::write(dir.path().join("README.md"), "").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        let output = GlobTool
+            .call(&ctx, json!({"pattern": "**/*.rs"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "src/a.rs\nsrc/nested/b.rs");
+
+        let output = GlobTool
+            .call(&ctx, json!({"pattern": "src/*.rs"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "src/a.rs");
+
+        let output = GlobTool
+            .call(&ctx, json!({"pattern": "*.rs", "path": "src"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "src/a.rs");
+
+        let output = GlobTool
+            .call(
+                &ctx,
+                json!({"pattern": "**/*.rs", "path": "src", "sort": "size", "limit": 1}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            output,
+            "src/a.rs\n... 1 more files; narrow the pattern or raise limit"
+        );
+    }
+}
@@ -0,0 +1,393 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use super::{Access, Result, Tool, ToolContext, object_schema, optional_str};
+use crate::detect::sniff_binary;
+use crate::models::{AppError, ToolSchema};
+
+/// Entries returned when the model does not pass a `limit`.
+const DEFAULT_LIMIT: usize = 1000;
+/// Files larger than this are not read to count their lines.
+const MAX_LINE_COUNT_BYTES: u64 = 1024 * 1024;
+
+/// Lists files and directories recursively, skipping the packer's excluded paths.
+pub struct ListFilesTool;
+
+#[async_trait]
+impl Tool for ListFilesTool {
+    fn name(&self) -> &str {
+        "list_files"
+    }
+
+    fn description(&self) -> &str {
+        "List files and directories recursively starting from a given path. If the path is a file, lists only that file. If no path is provided, lists files in the current directory. Directories end with '/'. Use max_depth to stay shallow in large projects."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[
+                (
+                    "path",
+                    "string",
+                    "Optional relative directory path to list files from. Defaults to current directory ('.') if not provided.",
+                ),
+                (
+                    "max_depth",
+                    "integer",
+                    "Optional depth limit; 1 lists only the direct children of the path.",
+                ),
+                (
+                    "limit",
+                    "integer",
+                    "Maximum number of entries to return (default 1000).",
+                ),
+                (
+                    "sort",
+                    "string",
+                    "Order of entries within each directory: 'name' (default), 'modified' (newest first) or 'size' (largest first).",
+                ),
+                (
+                    "details",
+                    "boolean",
+                    "Annotate files with their size and line count.",
+                ),
+            ],
+            &[],
+        )
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let start_path_str = optional_str(&input, "path").unwrap_or(".");
+        let start_path = ctx.resolve(start_path_str, Access::Read)?;
+        let options = ListOptions::from_input(&input)?;
+
+        let mut lister = Lister {
+            ctx,
+            base: &start_path,
+            display_base: Path::new(start_path_str),
+            options: &options,
+            files: Vec::new(),
+            total: 0,
+        };
+        lister.visit(&start_path, 0)?;
+
+        let mut files = lister.files;
+        if lister.total > files.len() {
+            files.push(format!(
+                "... {} more entries not shown; raise limit, lower max_depth or list a subdirectory",
+                lister.total - files.len()
+            ));
+        }
+        serde_json::to_string(&files)
+            .map_err(|e| AppError(format!("Failed to serialize file list: {}", e)))
+    }
+}
+
+/// Order of entries within a directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SortKey {
+    Name,
+    /// Most recently modified first.
+    Modified,
+    /// Largest first.
+    Size,
+}
+
+impl SortKey {
+    pub(crate) fn parse(value: Option<&str>) -> Result<Self> {
+        match value.unwrap_or("name") {
+            "name" => Ok(SortKey::Name),
+            "modified" => Ok(SortKey::Modified),
+            "size" => Ok(SortKey::Size),
+            other => Err(AppError(format!(
+                "Invalid sort '{}'; use 'name', 'modified' or 'size'",
+                other
+            ))),
+        }
+    }
+
+    pub(crate) fn sort(self, entries: &mut [(PathBuf, Metadata)]) {
+        match self {
+            SortKey::Name => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+            SortKey::Modified => entries.sort_by_key(|(path, meta)| {
+                (Reverse(meta.modified().unwrap_or(UNIX_EPOCH)), path.clone())
+            }),
+            SortKey::Size => {
+                entries.sort_by_key(|(path, meta)| (Reverse(meta.len()), path.clone()))
+            }
+        }
+    }
+}
+
+struct ListOptions {
+    max_depth: Option<usize>,
+    limit: usize,
+    sort: SortKey,
+    details: bool,
+}
+
+impl ListOptions {
+    fn from_input(input: &Value) -> Result<Self> {
+        let number = |key| input.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+        Ok(ListOptions {
+            max_depth: number("max_depth").filter(|&d| d > 0),
+            limit: number("limit").filter(|&n| n > 0).unwrap_or(DEFAULT_LIMIT),
+            sort: SortKey::parse(optional_str(input, "sort"))?,
+            details: input
+                .get("details")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false),
+        })
+    }
+}
+
+/// Human-readable byte count, e.g. `512 B` or `2.4 KB`.
+pub(crate) fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// `path (size, N lines)`, or `path (size, binary)`.
+pub(crate) fn annotate(display: String, path: &Path, size: u64) -> String {
+    let lines = if size > MAX_LINE_COUNT_BYTES {
+        None
+    } else {
+        fs::read(path).ok().map(|bytes| {
+            if sniff_binary(&bytes).is_some() {
+                "binary".to_string()
+            } else {
+                let lines = count_lines(&bytes);
+                format!("{} line{}", lines, if lines == 1 { "" } else { "s" })
+            }
+        })
+    };
+    match lines {
+        Some(lines) => format!("{} ({}, {})", display, format_size(size), lines),
+        None => format!("{} ({})", display, format_size(size)),
+    }
+}
+
+fn count_lines(bytes: &[u8]) -> usize {
+    let newlines = bytes.iter().filter(|&&b| b == b'\n').count();
+    newlines + usize::from(bytes.last().is_some_and(|&b| b != b'\n'))
+}
+
+struct Lister<'a> {
+    ctx: &'a ToolContext,
+    base: &'a Path,
+    /// The path as given by the model; listed paths start with it.
+    display_base: &'a Path,
+    options: &'a ListOptions,
+    files: Vec<String>,
+    /// Entries seen, including those past the limit.
+    total: usize,
+}
+
+impl Lister<'_> {
+    fn display(&self, path: &Path) -> String {
+        match path.strip_prefix(self.base) {
+            Ok(rest) if !rest.as_os_str().is_empty() => self.display_base.join(rest),
+            _ => self.display_base.to_path_buf(),
+        }
+        .to_string_lossy()
+        .to_string()
+    }
+
+    /// Skips excluded paths and symlinks leading out of the workspace.
+    fn skipped(&self, path: &Path) -> bool {
+        self.ctx.is_skipped(path)
+            || !path
+                .canonicalize()
+                .is_ok_and(|resolved| resolved.starts_with(self.ctx.root()))
+    }
+
+    fn push(&mut self, entry: String) {
+        self.total += 1;
+        if self.files.len() < self.options.limit {
+            self.files.push(entry);
+        }
+    }
+
+    fn push_file(&mut self, path: &Path, size: u64) {
+        // Annotating reads the file, so skip it once past the limit.
+        let display = self.display(path);
+        if self.options.details && self.files.len() < self.options.limit {
+            self.push(annotate(display, path, size));
+        } else {
+            self.push(display);
+        }
+    }
+
+    fn visit(&mut self, current_path: &Path, depth: usize) -> Result<()> {
+        if !current_path.exists() {
+            return Err(AppError(format!(
+                "Path does not exist: {}",
+                self.display(current_path)
+            )));
+        }
+
+        if current_path.is_file() {
+            if !self.skipped(current_path) {
+                let size = fs::metadata(current_path).map_or(0, |m| m.len());
+                self.push_file(current_path, size);
+            }
+            return Ok(());
+        }
+
+        let entries = fs::read_dir(current_path).map_err(|e| {
+            AppError(format!(
+                "Failed to read directory '{}': {}",
+                current_path.display(),
+                e
+            ))
+        })?;
+        let mut children = Vec::new();
+        for entry_result in entries {
+            match entry_result.and_then(|entry| {
+                let path = entry.path();
+                fs::metadata(&path).map(|meta| (path, meta))
+            }) {
+                Ok((path, meta)) if !self.skipped(&path) => children.push((path, meta)),
+                Ok(_) => {}
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to read entry in '{}': {}. Skipping.",
+                        current_path.display(),
+                        e
+                    );
+                }
+            }
+        }
+        self.options.sort.sort(&mut children);
+
+        for (path, meta) in children {
+            if meta.is_dir() {
+                let display = self.display(&path);
+                self.push(format!("{}/", display));
+                if self
+                    .options
+                    .max_depth
+                    .is_none_or(|max_depth| depth + 1 < max_depth)
+                {
+                    self.visit(&path, depth + 1)?;
+                }
+            } else if meta.is_file() {
+                self.push_file(&path, meta.len());
+            } else {
+                eprintln!(
+                    "Warning: Skipping non-directory/non-file path: {}",
+                    path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*; // Import items from the outer module
+    use crate::filter::PathFilter;
+    use crate::tools::Workspace;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn should_skip_tool_path(path: &Path) -> bool {
+        PathFilter::default().is_skipped(path)
+    }
+
+    #[test]
+    fn test_should_skip_tool_path_hidden() {
+        assert!(should_skip_tool_path(Path::new(".git")));
+        assert!(should_skip_tool_path(Path::new(".env")));
+        assert!(should_skip_tool_path(Path::new("src/.hidden_file")));
+        assert!(should_skip_tool_path(Path::new(".config/settings.toml")));
+    }
+
+    #[test]
+    fn test_should_skip_tool_path_build_dirs() {
+        assert!(should_skip_tool_path(Path::new("target")));
+        assert!(should_skip_tool_path(Path::new("node_modules")));
+        assert!(should_skip_tool_path(Path::new("project/target/debug")));
+        assert!(should_skip_tool_path(Path::new("app/node_modules/package")));
+        assert!(should_skip_tool_path(Path::new("venv/lib/python")));
+    }
+
+    #[test]
+    fn test_should_skip_tool_path_valid() {
+        assert!(!should_skip_tool_path(Path::new("src/main.rs")));
+        assert!(!should_skip_tool_path(Path::new("README.md")));
+        assert!(!should_skip_tool_path(Path::new("scripts/build.sh")));
+        assert!(!should_skip_tool_path(Path::new("docs/api.html")));
+        assert!(!should_skip_tool_path(Path::new(
+            ".github/workflows/ci.yml"
+        ))); // .github is allowed
+    }
+
+    #[tokio::test]
+    async fn test_list_files_uses_context_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("fixtures")).unwrap();
+        fs::write(dir.path().join("fixtures/a.json"), "").unwrap();
+        fs::write(dir.path().join("main.rs"), "").unwrap();
+
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap())
+            .with_filter(PathFilter::default().with_ignore(&["fixtures".to_string()]));
+        let listed = ListFilesTool.call(&ctx, json!({})).await.unwrap();
+        assert_eq!(listed, r#"["./main.rs"]"#);
+    }
+
+    #[tokio::test]
+    async fn test_list_files_relative_to_root() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "").unwrap();
+
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+        let listed = ListFilesTool.call(&ctx, json!({})).await.unwrap();
+        assert_eq!(listed, r#"["./src/","./src/main.rs"]"#);
+
+        let listed = ListFilesTool
+            .call(&ctx, json!({"path": "src"}))
+            .await
+            .unwrap();
+        assert_eq!(listed, r#"["src/main.rs"]"#);
+    }
+
+    #[tokio::test]
+    async fn test_list_files_depth_limit_and_details() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a/deep")).unwrap();
+        fs::write(dir.path().join("a/deep/x.rs"), "").unwrap();
+        fs::write(dir.path().join("b.rs"), "one\ntwo\n").unwrap();
+        fs::write(dir.path().join("c.rs"), "1").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        let listed = ListFilesTool
+            .call(
+                &ctx,
+                json!({"max_depth": 1, "details": true, "sort": "size"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            listed,
+            r#"["./a/","./b.rs (8 B, 2 lines)","./c.rs (1 B, 1 line)"]"#
+        );
+
+        let listed = ListFilesTool.call(&ctx, json!({"limit": 2})).await.unwrap();
+        assert!(
+            listed.starts_with(r#"["./a/","./a/deep/","... 3 more entries"#),
+            "{}",
+            listed
+        );
+    }
+}
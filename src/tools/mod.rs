@@ -0,0 +1,307 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::CodeConfig;
+use crate::filter::PathFilter;
+use crate::models::{AppError, ToolSchema, ToolSchemaProperty};
+
+pub mod apply_patch;
+pub mod checkpoint;
+pub mod command;
+pub mod diff;
+pub mod edit_file;
+pub mod glob;
+pub mod list_files;
+pub mod permissions;
+pub mod read_file;
+pub mod run_command;
+pub mod search;
+pub mod str_replace;
+pub mod workspace;
+
+pub use apply_patch::ApplyPatchTool;
+pub use command::CommandTool;
+pub use edit_file::EditFileTool;
+pub use glob::GlobTool;
+pub use list_files::ListFilesTool;
+pub use permissions::{PermissionConfig, Permissions, Policy};
+pub use read_file::ReadFileTool;
+pub use run_command::{RunCommandConfig, RunCommandTool};
+pub use search::SearchTool;
+pub use str_replace::StrReplaceTool;
+pub use workspace::{Access, SandboxConfig, Workspace};
+
+type Result<T> = std::result::Result<T, AppError>;
+
+/// State shared by every tool call.
+///
+/// Tools get filesystem paths only through [`ToolContext::resolve`], so the
+/// workspace sandbox applies to every tool alike.
+#[derive(Debug, Clone)]
+pub struct ToolContext {
+    pub workspace: Workspace,
+    /// Paths hidden from listings and searches; the packer's rules, so the
+    /// agent sees the same files `pai md` would pack.
+    pub filter: PathFilter,
+}
+
+impl ToolContext {
+    pub fn new(workspace: Workspace) -> Self {
+        ToolContext {
+            workspace,
+            filter: PathFilter::default(),
+        }
+    }
+
+    /// Uses `filter`, e.g. built from the project's `[filter]` section.
+    pub fn with_filter(mut self, filter: PathFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Whether listing and search tools skip `path` (absolute or root-relative).
+    pub fn is_skipped(&self, path: &Path) -> bool {
+        self.filter.is_skipped(self.relative(path))
+    }
+
+    pub fn root(&self) -> &Path {
+        self.workspace.root()
+    }
+
+    /// Resolves a path argument inside the workspace, enforcing its rules for `access`.
+    pub fn resolve(&self, path: &str, access: Access) -> Result<PathBuf> {
+        self.workspace.resolve(path, access)
+    }
+
+    /// `path` relative to the workspace root, for display and path rules.
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        self.workspace.relative(path)
+    }
+}
+
+/// A capability the coding agent can invoke.
+///
+/// Tools are registered in a [`ToolRegistry`], which advertises them to the
+/// model and dispatches its tool calls.
+#[async_trait]
+pub trait Tool: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn description(&self) -> &str;
+
+    /// JSON schema of the tool's input object.
+    fn schema(&self) -> ToolSchema;
+
+    /// Whether calls change the workspace and so need approval by default.
+    fn is_mutating(&self) -> bool {
+        false
+    }
+
+    /// Paths a call touches, matched against the `[code.permissions]` path rules.
+    fn paths(&self, input: &Value) -> Vec<String> {
+        optional_str(input, "path")
+            .map(|path| vec![path.to_string()])
+            .unwrap_or_default()
+    }
+
+    /// The shell command a call runs, matched against the allowlisted prefixes.
+    fn command(&self, _input: &Value) -> Option<String> {
+        None
+    }
+
+    /// What a call will do; shown before every mutating call, whatever the policy.
+    fn preview(&self, _ctx: &ToolContext, input: &Value) -> String {
+        serde_json::to_string_pretty(input).unwrap_or_else(|_| input.to_string())
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String>;
+}
+
+/// Tools available to the agent, in registration order.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: Vec<Box<dyn Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with the built-in file tools.
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(Box::new(ReadFileTool));
+        registry.register(Box::new(ListFilesTool));
+        registry.register(Box::new(GlobTool));
+        registry.register(Box::new(SearchTool));
+        registry.register(Box::new(EditFileTool));
+        registry.register(Box::new(StrReplaceTool));
+        registry.register(Box::new(ApplyPatchTool));
+        registry.register(Box::new(RunCommandTool::new(RunCommandConfig::default())));
+        registry
+    }
+
+    /// Adds the command tools defined in `.pai.toml`, applies the `run_command`
+    /// limits and drops disabled tools.
+    pub fn apply_config(&mut self, config: &CodeConfig) {
+        if self.get("run_command").is_some() {
+            self.register(Box::new(RunCommandTool::new(config.run_command.clone())));
+        }
+        for tool in &config.tools {
+            self.register(Box::new(CommandTool::from_config(
+                tool,
+                &config.run_command,
+            )));
+        }
+        self.tools
+            .retain(|tool| !config.disabled_tools.iter().any(|name| name == tool.name()));
+    }
+
+    /// Adds `tool`, replacing any registered tool with the same name.
+    pub fn register(&mut self, tool: Box<dyn Tool>) {
+        match self.tools.iter().position(|t| t.name() == tool.name()) {
+            Some(index) => self.tools[index] = tool,
+            None => self.tools.push(tool),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Tool> {
+        self.tools
+            .iter()
+            .find(|t| t.name() == name)
+            .map(|t| t.as_ref())
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools.iter().map(|t| t.name())
+    }
+
+    /// Tool descriptions in the form sent to the model.
+    pub fn api_tools(&self) -> Vec<crate::models::Tool> {
+        self.tools
+            .iter()
+            .map(|tool| crate::models::Tool {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                input_schema: tool.schema(),
+            })
+            .collect()
+    }
+
+    pub async fn call(&self, ctx: &ToolContext, name: &str, input: Value) -> Result<String> {
+        let tool = self
+            .get(name)
+            .ok_or_else(|| AppError(format!("Tool '{}' not found.", name)))?;
+        tool.call(ctx, input).await
+    }
+}
+
+/// Builds an object schema from `(name, type, description)` triples.
+pub fn object_schema(properties: &[(&str, &str, &str)], required: &[&str]) -> ToolSchema {
+    ToolSchema {
+        schema_type: "object".to_string(),
+        properties: properties
+            .iter()
+            .map(|(name, property_type, description)| {
+                (
+                    name.to_string(),
+                    ToolSchemaProperty {
+                        property_type: property_type.to_string(),
+                        description: description.to_string(),
+                        items: None,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>(),
+        required: Some(required.iter().map(|r| r.to_string()).collect()),
+    }
+}
+
+/// Reads a required, non-empty string argument.
+pub fn required_str<'a>(input: &'a Value, key: &str, tool: &str) -> Result<&'a str> {
+    optional_str(input, key).ok_or_else(|| {
+        AppError(format!(
+            "Missing or empty required '{}' parameter for {}",
+            key, tool
+        ))
+    })
+}
+
+/// Reads an optional string argument, treating blank strings as absent.
+pub fn optional_str<'a>(input: &'a Value, key: &str) -> Option<&'a str> {
+    input
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::command::CommandToolConfig;
+    use serde_json::json;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Tool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn description(&self) -> &str {
+            "Echoes its input."
+        }
+
+        fn schema(&self) -> ToolSchema {
+            object_schema(&[("text", "string", "Text to echo.")], &["text"])
+        }
+
+        async fn call(&self, _ctx: &ToolContext, input: Value) -> Result<String> {
+            Ok(required_str(&input, "text", "echo")?.to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry_dispatch_and_config() {
+        let mut registry = ToolRegistry::with_defaults();
+        registry.register(Box::new(Echo));
+        registry.apply_config(&CodeConfig {
+            disabled_tools: vec!["edit_file".to_string()],
+            tools: vec![CommandToolConfig {
+                name: "check".to_string(),
+                description: "Run checks".to_string(),
+                command: "true".to_string(),
+            }],
+            ..CodeConfig::default()
+        });
+
+        let names: Vec<&str> = registry.names().collect();
+        assert_eq!(
+            names,
+            vec![
+                "read_file",
+                "list_files",
+                "glob",
+                "search",
+                "str_replace",
+                "apply_patch",
+                "run_command",
+                "echo",
+                "check"
+            ]
+        );
+
+        let ctx = ToolContext::new(Workspace::new(".").unwrap());
+        let output = registry
+            .call(&ctx, "echo", json!({"text": "hi"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "hi");
+        assert!(registry.call(&ctx, "edit_file", json!({})).await.is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use globset::{Glob, GlobMatcher};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use super::Result;
+use crate::models::AppError;
+
+/// What happens when the agent calls a tool.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Policy {
+    /// Run without asking.
+    Allow,
+    /// Show a preview and ask before running.
+    Ask,
+    /// Refuse and tell the model the call was blocked.
+    Deny,
+}
+
+fn default_policy() -> Policy {
+    Policy::Ask
+}
+
+/// `[code.permissions]` section of `.pai.toml`.
+///
+/// Path rules are globs relative to the workspace root. A `deny` from either a
+/// tool or a path rule always wins; otherwise path rules take precedence over
+/// tool rules, which take precedence over `default`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PermissionConfig {
+    /// Policy for mutating tools without a more specific rule.
+    #[serde(default = "default_policy")]
+    pub default: Policy,
+    #[serde(default)]
+    pub tools: BTreeMap<String, Policy>,
+    #[serde(default)]
+    pub paths: BTreeMap<String, Policy>,
+    /// Command prefixes that run without asking, e.g. `"cargo test"`.
+    #[serde(default)]
+    pub allow_commands: Vec<String>,
+}
+
+impl Default for PermissionConfig {
+    fn default() -> Self {
+        PermissionConfig {
+            default: default_policy(),
+            tools: BTreeMap::new(),
+            paths: BTreeMap::new(),
+            allow_commands: Vec::new(),
+        }
+    }
+}
+
+/// The user's answer to an approval prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    AllowOnce,
+    AllowForSession,
+    /// Denied, with optional feedback passed back to the model.
+    Deny(String),
+}
+
+/// Decides which tool calls need approval and remembers session-wide approvals.
+#[derive(Debug, Clone)]
+pub struct Permissions {
+    config: PermissionConfig,
+    /// `config.paths`, compiled.
+    paths: Vec<(GlobMatcher, Policy)>,
+    /// Approve everything that is not explicitly denied (`--yes`).
+    yes: bool,
+    session_allowed: HashSet<String>,
+}
+
+impl Permissions {
+    /// Fails if a path rule is not a valid glob.
+    pub fn new(config: PermissionConfig, yes: bool) -> Result<Self> {
+        let paths = config
+            .paths
+            .iter()
+            .map(|(pattern, rule)| {
+                let matcher = Glob::new(pattern)
+                    .map_err(|e| {
+                        AppError(format!("Invalid permission pattern '{}': {}", pattern, e))
+                    })?
+                    .compile_matcher();
+                Ok((matcher, *rule))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Permissions {
+            config,
+            paths,
+            yes,
+            session_allowed: HashSet::new(),
+        })
+    }
+
+    /// Most restrictive policy among the path rules matching any of `paths`.
+    fn path_policy(&self, paths: &[&Path]) -> Option<Policy> {
+        self.paths
+            .iter()
+            .filter(|(matcher, _)| paths.iter().any(|path| matcher.is_match(path)))
+            .map(|(_, rule)| *rule)
+            .max()
+    }
+
+    /// Whether `command` starts with an allowlisted prefix and does nothing more.
+    ///
+    /// Commands that chain, pipe, redirect or substitute are never allowlisted,
+    /// since the prefix would not describe everything they run.
+    fn command_allowed(&self, command: &str) -> bool {
+        let command = command.trim();
+        if command.contains([';', '&', '|', '`', '>', '<', '\n']) || command.contains("$(") {
+            return false;
+        }
+        self.config.allow_commands.iter().any(|prefix| {
+            command
+                .strip_prefix(prefix.trim())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+    }
+
+    /// Policy for calling `tool` on `paths` (relative to the workspace root);
+    /// `command` is the shell command the call runs, if any.
+    pub fn decide(
+        &self,
+        tool: &str,
+        mutating: bool,
+        paths: &[&Path],
+        command: Option<&str>,
+    ) -> Policy {
+        let tool_policy = self.config.tools.get(tool).copied();
+        let path_policy = self.path_policy(paths);
+
+        if tool_policy == Some(Policy::Deny) || path_policy == Some(Policy::Deny) {
+            return Policy::Deny;
+        }
+        if self.yes
+            || self.session_allowed.contains(tool)
+            || command.is_some_and(|c| self.command_allowed(c))
+        {
+            return Policy::Allow;
+        }
+
+        let fallback = if mutating {
+            self.config.default
+        } else {
+            Policy::Allow
+        };
+        path_policy.or(tool_policy).unwrap_or(fallback)
+    }
+
+    pub fn allow_for_session(&mut self, tool: &str) {
+        self.session_allowed.insert(tool.to_string());
+    }
+}
+
+/// Asks whether `tool` may run; the caller has already shown what it will do.
+pub fn ask<R: BufRead, W: Write>(reader: &mut R, out: &mut W, tool: &str) -> Result<Answer> {
+    let io_error = |e: std::io::Error| AppError(e.to_string());
+    writeln!(out, "\x1b[95mapproval\x1b[0m: {} wants to run", tool).map_err(io_error)?;
+
+    loop {
+        write!(
+            out,
+            "Allow? [y] once / [a] always this session / [n] deny: "
+        )
+        .map_err(io_error)?;
+        out.flush().map_err(io_error)?;
+
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(io_error)? == 0 {
+            return Ok(Answer::Deny(String::new()));
+        }
+        match line.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Answer::AllowOnce),
+            "a" | "always" => return Ok(Answer::AllowForSession),
+            "n" | "no" => {
+                write!(out, "Feedback for the model (optional): ").map_err(io_error)?;
+                out.flush().map_err(io_error)?;
+                let mut feedback = String::new();
+                reader.read_line(&mut feedback).map_err(io_error)?;
+                return Ok(Answer::Deny(feedback.trim().to_string()));
+            }
+            _ => continue,
+        }
+    }
+}
+
+/// Tool result sent to the model when a call is not allowed to run.
+pub fn denial_message(tool: &str, feedback: &str, by_policy: bool) -> String {
+    let mut message = if by_policy {
+        format!("The {} call was blocked by the permission policy.", tool)
+    } else {
+        format!("The user denied this {} call.", tool)
+    };
+    if !feedback.is_empty() {
+        message.push_str(&format!(" Feedback: {}", feedback));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_precedence() {
+        let config: PermissionConfig = toml::from_str(
+            r#"
+            allow_commands = ["cargo test", "git status"]
+
+            [tools]
+            run_tests = "allow"
+            edit_file = "ask"
+
+            [paths]
+            "src/**" = "allow"
+            "Cargo.toml" = "deny"
+            "#,
+        )
+        .unwrap();
+        let mut permissions = Permissions::new(config, false).unwrap();
+        let path = |p| vec![Path::new(p)];
+
+        assert_eq!(
+            permissions.decide("read_file", false, &path("README.md"), None),
+            Policy::Allow
+        );
+        assert_eq!(
+            permissions.decide("edit_file", true, &path("README.md"), None),
+            Policy::Ask
+        );
+        assert_eq!(
+            permissions.decide("edit_file", true, &path("src/lib.rs"), None),
+            Policy::Allow
+        );
+        assert_eq!(
+            permissions.decide("run_tests", true, &[], None),
+            Policy::Allow
+        );
+        assert_eq!(permissions.decide("new_tool", true, &[], None), Policy::Ask);
+
+        let run = |command| permissions.decide("run_command", true, &[], Some(command));
+        assert_eq!(run("cargo test -p pai"), Policy::Allow);
+        assert_eq!(run("git status"), Policy::Allow);
+        assert_eq!(run("cargo testing"), Policy::Ask);
+        assert_eq!(run("cargo test && rm -rf /"), Policy::Ask);
+        assert_eq!(run("git status $(rm -rf /)"), Policy::Ask);
+
+        permissions.allow_for_session("edit_file");
+        assert_eq!(
+            permissions.decide("edit_file", true, &path("README.md"), None),
+            Policy::Allow
+        );
+        assert_eq!(
+            permissions.decide("edit_file", true, &path("Cargo.toml"), None),
+            Policy::Deny
+        );
+
+        let yes = Permissions::new(PermissionConfig::default(), true).unwrap();
+        assert_eq!(
+            yes.decide("edit_file", true, &path("a.rs"), None),
+            Policy::Allow
+        );
+
+        let typo: PermissionConfig = toml::from_str("[paths]\n\"src/[a\" = \"deny\"\n").unwrap();
+        assert!(Permissions::new(typo, false).is_err());
+    }
+
+    #[test]
+    fn test_ask_reads_answer_and_feedback() {
+        let mut out = Vec::new();
+        let mut input = "maybe\nn\nUse a smaller change.\n".as_bytes();
+        let answer = ask(&mut input, &mut out, "edit_file").unwrap();
+        assert_eq!(answer, Answer::Deny("Use a smaller change.".to_string()));
+
+        let mut input = "a\n".as_bytes();
+        let answer = ask(&mut input, &mut out, "edit_file").unwrap();
+        assert_eq!(answer, Answer::AllowForSession);
+    }
+}
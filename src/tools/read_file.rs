@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::fs;
+
+use super::{Access, Result, Tool, ToolContext, object_schema, required_str};
+use crate::detect::{decode_text, sniff_binary};
+use crate::models::{AppError, ToolSchema};
+
+/// Lines returned when the model does not pass a `limit`.
+const DEFAULT_LINE_LIMIT: usize = 2000;
+/// Longest line returned before it is cut.
+const MAX_LINE_CHARS: usize = 2000;
+
+/// Returns a range of a file's lines, numbered.
+pub struct ReadFileTool;
+
+#[async_trait]
+impl Tool for ReadFileTool {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read a file given its relative path. Lines are returned prefixed with their line number and a tab; the prefix is not part of the file. Long files are cut after 2000 lines; pass 'offset' and 'limit' to read a specific range."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[
+                ("path", "string", "The relative path of the file to read."),
+                (
+                    "offset",
+                    "integer",
+                    "Optional 1-based line number to start reading from.",
+                ),
+                (
+                    "limit",
+                    "integer",
+                    "Optional maximum number of lines to read (default 2000).",
+                ),
+            ],
+            &["path"],
+        )
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let path = required_str(&input, "path", "read_file")?;
+        let number = |key| input.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+        let offset = number("offset").unwrap_or(1).max(1);
+        let limit = number("limit").unwrap_or(DEFAULT_LINE_LIMIT).max(1);
+
+        let resolved = ctx.resolve(path, Access::Read)?;
+        let bytes = fs::read(&resolved)
+            .map_err(|e| AppError(format!("Failed to read file '{}': {}", path, e)))?;
+        if let Some(kind) = sniff_binary(&bytes) {
+            return Err(AppError(format!(
+                "'{}' is a binary file ({}) and cannot be read as text",
+                path, kind
+            )));
+        }
+        // Same decoding as `search`, so UTF-16 files with a BOM are readable.
+        let content = decode_text(bytes).ok_or_else(|| {
+            AppError(format!(
+                "'{}' is not valid UTF-8 or UTF-16 text and cannot be read",
+                path
+            ))
+        })?;
+
+        let total = content.lines().count();
+        if total == 0 {
+            return Ok(format!("'{}' is empty.", path));
+        }
+        if offset > total {
+            return Err(AppError(format!(
+                "offset {} is past the end of '{}' ({} lines)",
+                offset, path, total
+            )));
+        }
+
+        let mut out: Vec<String> = content
+            .lines()
+            .enumerate()
+            .skip(offset - 1)
+            .take(limit)
+            .map(
+                |(index, line)| match line.char_indices().nth(MAX_LINE_CHARS) {
+                    Some((end, _)) => format!("{:>6}\t{}... [line cut]", index + 1, &line[..end]),
+                    None => format!("{:>6}\t{}", index + 1, line),
+                },
+            )
+            .collect();
+
+        let shown_to = (offset - 1).saturating_add(limit).min(total);
+        if shown_to < total {
+            out.push(format!(
+                "[file truncated, {} more lines; read on with offset={}]",
+                total - shown_to,
+                shown_to + 1
+            ));
+        }
+        Ok(out.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Workspace;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_read_file_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let lines: Vec<String> = (1..=2500).map(|n| format!("line {}", n)).collect();
+        fs::write(dir.path().join("long.txt"), lines.join("\n")).unwrap();
+        fs::write(dir.path().join("image.png"), b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        fs::write(dir.path().join("utf16.txt"), b"\xff\xfeh\0i\0").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        let output = ReadFileTool
+            .call(&ctx, json!({"path": "long.txt"}))
+            .await
+            .unwrap();
+        assert!(output.starts_with("     1\tline 1\n"));
+        assert!(output.ends_with(
+            "  2000\tline 2000\n[file truncated, 500 more lines; read on with offset=2001]"
+        ));
+
+        let output = ReadFileTool
+            .call(
+                &ctx,
+                json!({"path": "long.txt", "offset": 2499, "limit": 5}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output, "  2499\tline 2499\n  2500\tline 2500");
+
+        let output = ReadFileTool
+            .call(
+                &ctx,
+                json!({"path": "long.txt", "offset": 2500, "limit": u64::MAX}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(output, "  2500\tline 2500");
+
+        let output = ReadFileTool
+            .call(&ctx, json!({"path": "utf16.txt"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "     1\thi");
+
+        assert!(
+            ReadFileTool
+                .call(&ctx, json!({"path": "image.png"}))
+                .await
+                .unwrap_err()
+                .0
+                .contains("binary")
+        );
+    }
+}
@@ -0,0 +1,328 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::Notify;
+
+use super::{Result, Tool, ToolContext, object_schema, required_str};
+use crate::models::{AppError, ToolSchema};
+
+/// Commands currently running; Ctrl-C interrupts them instead of quitting.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+/// Wakes running commands when the Ctrl-C listener decides to interrupt them.
+static INTERRUPT: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+fn default_timeout_secs() -> u64 {
+    120
+}
+
+fn default_max_output_bytes() -> usize {
+    30_000
+}
+
+/// `[code.run_command]` section of `.pai.toml`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RunCommandConfig {
+    /// Longest a command may run before its process group is killed.
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    /// Output kept per stream; the middle of longer output is cut.
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+}
+
+impl Default for RunCommandConfig {
+    fn default() -> Self {
+        RunCommandConfig {
+            timeout_secs: default_timeout_secs(),
+            max_output_bytes: default_max_output_bytes(),
+        }
+    }
+}
+
+/// Keeps the first and last `limit / 2` bytes of a stream.
+struct Capture {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    dropped: usize,
+    half: usize,
+}
+
+impl Capture {
+    fn new(limit: usize) -> Self {
+        Capture {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            dropped: 0,
+            half: limit / 2,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.head.len() < self.half {
+                self.head.push(byte);
+            } else {
+                self.tail.push_back(byte);
+                if self.tail.len() > self.half {
+                    self.tail.pop_front();
+                    self.dropped += 1;
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> String {
+        let mut out = String::from_utf8_lossy(&self.head).to_string();
+        if self.dropped > 0 {
+            out.push_str(&format!("\n[... {} bytes truncated ...]\n", self.dropped));
+        }
+        let tail: Vec<u8> = self.tail.into_iter().collect();
+        out.push_str(&String::from_utf8_lossy(&tail));
+        out
+    }
+}
+
+async fn capture<R: AsyncRead + Unpin>(mut stream: R, limit: usize) -> String {
+    let mut capture = Capture::new(limit);
+    let mut buffer = [0u8; 8192];
+    while let Ok(n) = stream.read(&mut buffer).await {
+        if n == 0 {
+            break;
+        }
+        capture.push(&buffer[..n]);
+    }
+    capture.finish()
+}
+
+#[cfg(unix)]
+fn kill_group(pid: u32) {
+    // The child leads its own process group, so this also reaches its children.
+    unsafe {
+        libc::kill(-(pid as i32), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: u32) {}
+
+/// Interrupts the running commands. Returns `false` if none was running.
+fn interrupt_commands() -> bool {
+    if RUNNING.load(Ordering::SeqCst) == 0 {
+        return false;
+    }
+    INTERRUPT.notify_waiters();
+    true
+}
+
+/// Quits the agent on Ctrl-C unless a command is running, in which case only
+/// the command is interrupted. Call once before running commands; it is the
+/// only Ctrl-C listener, so the decision is made in one place.
+pub fn exit_on_ctrl_c_when_idle() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if !interrupt_commands() {
+                std::process::exit(130);
+            }
+        }
+    });
+}
+
+/// Runs `command` with `sh -c` in `root`, killing its process group on timeout
+/// or Ctrl-C. Returns the exit status and captured output.
+pub async fn run_shell(
+    root: &Path,
+    command: &str,
+    timeout: Duration,
+    max_output_bytes: usize,
+) -> Result<String> {
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(command)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    #[cfg(unix)]
+    shell.process_group(0);
+
+    let mut child = shell
+        .spawn()
+        .map_err(|e| AppError(format!("Failed to run '{}': {}", command, e)))?;
+    let Some(pid) = child.id() else {
+        return Err(AppError(format!(
+            "'{}' exited before it could be tracked",
+            command
+        )));
+    };
+    let stdout = tokio::spawn(capture(child.stdout.take().unwrap(), max_output_bytes));
+    let stderr = tokio::spawn(capture(child.stderr.take().unwrap(), max_output_bytes));
+
+    // Created before the count goes up so an interrupt cannot slip between them.
+    let interrupted = INTERRUPT.notified();
+    RUNNING.fetch_add(1, Ordering::SeqCst);
+    let outcome = tokio::select! {
+        status = child.wait() => status
+            .map(|s| s.code().map_or_else(|| s.to_string(), |code| code.to_string()))
+            .map_err(|e| AppError(format!("Failed to wait for '{}': {}", command, e))),
+        _ = tokio::time::sleep(timeout) => {
+            kill_group(pid);
+            Ok(format!("timed out after {}s; process group killed", timeout.as_secs()))
+        }
+        _ = interrupted => {
+            kill_group(pid);
+            Ok("interrupted by the user (Ctrl-C); process group killed".to_string())
+        }
+    };
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+    let status = outcome?;
+    let _ = child.wait().await;
+
+    // Processes that left the group may still hold the pipes open.
+    let collect = |handle| async {
+        tokio::time::timeout(Duration::from_secs(2), handle)
+            .await
+            .ok()
+            .and_then(|joined: std::result::Result<String, _>| joined.ok())
+            .unwrap_or_default()
+    };
+    let stdout = collect(stdout).await;
+    let stderr = collect(stderr).await;
+    Ok(format!(
+        "exit status: {}\nstdout:\n{}\nstderr:\n{}",
+        status, stdout, stderr
+    ))
+}
+
+/// Runs a shell command chosen by the model in the workspace root.
+pub struct RunCommandTool {
+    config: RunCommandConfig,
+}
+
+impl RunCommandTool {
+    pub fn new(config: RunCommandConfig) -> Self {
+        RunCommandTool { config }
+    }
+
+    fn timeout(&self, input: &Value) -> Duration {
+        let requested = input
+            .get("timeout_secs")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(self.config.timeout_secs);
+        Duration::from_secs(requested.min(self.config.timeout_secs).max(1))
+    }
+}
+
+#[async_trait]
+impl Tool for RunCommandTool {
+    fn name(&self) -> &str {
+        "run_command"
+    }
+
+    fn description(&self) -> &str {
+        "Run a shell command (sh -c) in the workspace root, e.g. to build, test or inspect the project. Returns the exit status with stdout and stderr; long output is cut in the middle. Commands are killed after a timeout. Do not start interactive or long-running processes."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[
+                ("command", "string", "The shell command to run."),
+                (
+                    "timeout_secs",
+                    "integer",
+                    "Optional timeout in seconds; cannot exceed the configured limit.",
+                ),
+            ],
+            &["command"],
+        )
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn command(&self, input: &Value) -> Option<String> {
+        input
+            .get("command")
+            .and_then(|v| v.as_str())
+            .map(|c| c.trim().to_string())
+    }
+
+    fn preview(&self, ctx: &ToolContext, input: &Value) -> String {
+        format!(
+            "$ {}\n  (in {}, timeout {}s)",
+            self.command(input).unwrap_or_default(),
+            ctx.root().display(),
+            self.timeout(input).as_secs()
+        )
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let command = required_str(&input, "command", "run_command")?;
+        run_shell(
+            ctx.root(),
+            command,
+            self.timeout(&input),
+            self.config.max_output_bytes,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_run_shell_truncates_times_out_and_interrupts() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let output = run_shell(
+            dir.path(),
+            "seq 1 2000; echo oops >&2; exit 3",
+            Duration::from_secs(10),
+            100,
+        )
+        .await
+        .unwrap();
+        assert!(output.starts_with("exit status: 3\n"), "{}", output);
+        assert!(output.contains("bytes truncated"));
+        assert!(output.contains("2000\n"));
+        assert!(output.contains("stderr:\noops"));
+
+        let start = Instant::now();
+        let output = run_shell(
+            dir.path(),
+            "sleep 30 & sleep 30",
+            Duration::from_secs(1),
+            100,
+        )
+        .await
+        .unwrap();
+        assert!(output.contains("timed out after 1s"), "{}", output);
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        let start = Instant::now();
+        let running = tokio::spawn({
+            let root = dir.path().to_path_buf();
+            async move { run_shell(&root, "sleep 30", Duration::from_secs(60), 100).await }
+        });
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(interrupt_commands());
+        let output = running.await.unwrap().unwrap();
+        assert!(output.contains("interrupted by the user"), "{}", output);
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(!interrupt_commands());
+    }
+}
@@ -0,0 +1,282 @@
+use async_trait::async_trait;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::{Regex, RegexBuilder};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use super::{Access, Result, Tool, ToolContext, object_schema, optional_str, required_str};
+use crate::detect::{decode_text, sniff_binary};
+use crate::models::{AppError, ToolSchema};
+
+const DEFAULT_MAX_RESULTS: usize = 100;
+/// Longest line text shown per result.
+const MAX_LINE_CHARS: usize = 300;
+
+/// What to look for and where, parsed from the tool input.
+struct Query {
+    regex: Regex,
+    glob: Option<GlobMatcher>,
+    context: usize,
+    max_results: usize,
+}
+
+impl Query {
+    fn from_input(input: &Value) -> Result<Self> {
+        let pattern = required_str(input, "pattern", "search")?;
+        let flag = |key| input.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        let number = |key| input.get(key).and_then(|v| v.as_u64()).map(|n| n as usize);
+
+        let source = if flag("literal") {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(flag("case_insensitive"))
+            .build()
+            .map_err(|e| AppError(format!("Invalid search pattern '{}': {}", pattern, e)))?;
+
+        let glob = optional_str(input, "glob")
+            .map(|glob| {
+                GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()
+                    .map(|g| g.compile_matcher())
+                    .map_err(|e| AppError(format!("Invalid glob '{}': {}", glob, e)))
+            })
+            .transpose()?;
+
+        Ok(Query {
+            regex,
+            glob,
+            context: number("context").unwrap_or(0),
+            max_results: number("max_results")
+                .filter(|&n| n > 0)
+                .unwrap_or(DEFAULT_MAX_RESULTS),
+        })
+    }
+
+    /// Whether a file's root-relative path passes the glob; patterns without a
+    /// `/` also match the bare file name.
+    fn wants(&self, relative: &Path) -> bool {
+        self.glob.as_ref().is_none_or(|glob| {
+            glob.is_match(relative)
+                || (!glob.glob().glob().contains('/')
+                    && relative.file_name().is_some_and(|name| glob.is_match(name)))
+        })
+    }
+}
+
+fn clip(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_CHARS) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Appends `path:line: text` results (and `path-line- text` context) for one
+/// file. Returns the number of matching lines added.
+fn search_file(
+    query: &Query,
+    display: &str,
+    content: &str,
+    budget: usize,
+    out: &mut Vec<String>,
+) -> usize {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut found = 0;
+    // Next line not yet printed, so overlapping context is not repeated.
+    let mut printed_to = 0;
+
+    for (index, line) in lines.iter().enumerate() {
+        if found == budget {
+            break;
+        }
+        if !query.regex.is_match(line) {
+            continue;
+        }
+        found += 1;
+
+        let start = index.saturating_sub(query.context).max(printed_to);
+        if query.context > 0 && start > printed_to && !out.is_empty() {
+            out.push("--".to_string());
+        }
+        let end = (index + query.context).min(lines.len() - 1);
+        for (i, text) in lines.iter().enumerate().take(end + 1).skip(start) {
+            let is_match = i == index || (i > index && query.regex.is_match(text));
+            if i > index && is_match {
+                // Printed when the loop reaches it, with its own context.
+                break;
+            }
+            let separator = if is_match { ':' } else { '-' };
+            out.push(format!(
+                "{}{}{}{} {}",
+                display,
+                separator,
+                i + 1,
+                separator,
+                clip(text)
+            ));
+            printed_to = i + 1;
+        }
+    }
+    found
+}
+
+/// Searches file contents under a path with a regular expression.
+pub struct SearchTool;
+
+#[async_trait]
+impl Tool for SearchTool {
+    fn name(&self) -> &str {
+        "search"
+    }
+
+    fn description(&self) -> &str {
+        "Search file contents with a regular expression and list matching lines as 'path:line: text'. Skips the same build, dependency, hidden and binary files as list_files. Use this to find definitions and usages instead of reading whole files."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        object_schema(
+            &[
+                (
+                    "pattern",
+                    "string",
+                    "Regular expression to search for (Rust regex syntax).",
+                ),
+                (
+                    "path",
+                    "string",
+                    "Optional directory or file to search in, relative to the workspace. Defaults to '.'.",
+                ),
+                (
+                    "glob",
+                    "string",
+                    "Optional glob limiting which files are searched, e.g. '*.rs' or 'src/**/*.{ts,tsx}'.",
+                ),
+                ("case_insensitive", "boolean", "Ignore case when matching."),
+                (
+                    "literal",
+                    "boolean",
+                    "Treat the pattern as plain text instead of a regex.",
+                ),
+                (
+                    "context",
+                    "integer",
+                    "Lines of context to show around each match.",
+                ),
+                (
+                    "max_results",
+                    "integer",
+                    "Maximum number of matching lines to return (default 100).",
+                ),
+            ],
+            &["pattern"],
+        )
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let query = Query::from_input(&input)?;
+        let base = ctx.resolve(optional_str(&input, "path").unwrap_or("."), Access::Read)?;
+
+        let mut out = Vec::new();
+        let mut found = 0;
+        let walker = WalkDir::new(&base)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.path() == base || !ctx.is_skipped(entry.path()));
+
+        for entry in walker.filter_map(|e| e.ok()) {
+            if found == query.max_results {
+                out.push(format!(
+                    "[stopped after {} matches; narrow the pattern, path or glob to see more]",
+                    found
+                ));
+                break;
+            }
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = ctx.relative(entry.path());
+            if !query.wants(relative) || ctx.workspace.check(relative, Access::Read).is_err() {
+                continue;
+            }
+            let Ok(bytes) = fs::read(entry.path()) else {
+                continue;
+            };
+            if sniff_binary(&bytes).is_some() {
+                continue;
+            }
+            let Some(content) = decode_text(bytes) else {
+                continue;
+            };
+
+            let display = relative.to_string_lossy();
+            found += search_file(
+                &query,
+                &display,
+                &content,
+                query.max_results - found,
+                &mut out,
+            );
+        }
+
+        if out.is_empty() {
+            return Ok("No matches found.".to_string());
+        }
+        Ok(out.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Workspace;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_search_filters_and_context() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src")).unwrap();
+        fs::create_dir_all(dir.path().join("target")).unwrap();
+        fs::write(
+            dir.path().join("src/a.rs"),
+            "fn main() {\n    Run();\n}\nfn run() {}\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.md"), "run the tests\n").unwrap();
+        fs::write(dir.path().join("target/b.rs"), "fn run() {}\n").unwrap();
+        fs::write(dir.path().join("src/blob.rs"), b"run\0\0\0\0\0").unwrap();
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+        let search = |input| SearchTool.call(&ctx, input);
+
+        let output = search(json!({"pattern": r"fn \w+"})).await.unwrap();
+        assert_eq!(output, "src/a.rs:1: fn main() {\nsrc/a.rs:4: fn run() {}");
+
+        let output = search(
+            json!({"pattern": "run", "case_insensitive": true, "glob": "*.rs", "context": 1}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            output,
+            "src/a.rs-1- fn main() {\nsrc/a.rs:2:     Run();\nsrc/a.rs-3- }\nsrc/a.rs:4: fn run() {}"
+        );
+
+        let output = search(json!({"pattern": "run", "glob": "s*.rs"}))
+            .await
+            .unwrap();
+        assert_eq!(output, "No matches found.");
+
+        let output = search(json!({"pattern": "run", "max_results": 1}))
+            .await
+            .unwrap();
+        assert!(
+            output.starts_with("notes.md:1: run the tests\n[stopped after 1 matches"),
+            "{}",
+            output
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::fs;
+use std::path::Path;
+
+use super::{Access, Result, Tool, ToolContext, diff, object_schema, optional_str, required_str};
+use crate::models::{AppError, ToolSchema};
+
+/// One snippet replacement.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Edit {
+    pub old_str: String,
+    pub new_str: String,
+    #[serde(default)]
+    pub replace_all: bool,
+}
+
+/// Replaces exact snippets in a file, applying all edits or none.
+pub struct StrReplaceTool;
+
+impl StrReplaceTool {
+    /// Edits from either the `edits` array or the top-level `old_str`/`new_str` pair.
+    fn edits(input: &Value) -> Result<Vec<Edit>> {
+        match input.get("edits") {
+            Some(edits) => {
+                let edits: Vec<Edit> = serde_json::from_value(edits.clone())
+                    .map_err(|e| AppError(format!("Invalid 'edits' for str_replace: {}", e)))?;
+                if edits.is_empty() {
+                    return Err(AppError("'edits' for str_replace is empty".to_string()));
+                }
+                Ok(edits)
+            }
+            None => serde_json::from_value(input.clone())
+                .map(|edit| vec![edit])
+                .map_err(|e| {
+                    AppError(format!(
+                        "str_replace needs 'old_str' and 'new_str' or an 'edits' array: {}",
+                        e
+                    ))
+                }),
+        }
+    }
+}
+
+/// Applies `edits` in order to `content`, failing on the first snippet that is
+/// missing or ambiguous.
+pub fn apply_edits(path: &str, content: &str, edits: &[Edit]) -> Result<String> {
+    let mut content = content.to_string();
+    for (index, edit) in edits.iter().enumerate() {
+        let which = if edits.len() > 1 {
+            format!(" (edit {} of {})", index + 1, edits.len())
+        } else {
+            String::new()
+        };
+
+        if edit.old_str.is_empty() {
+            return Err(AppError(format!("old_str is empty{}", which)));
+        }
+        if edit.old_str == edit.new_str {
+            return Err(AppError(format!(
+                "old_str and new_str are identical{}",
+                which
+            )));
+        }
+
+        match content.matches(&edit.old_str).count() {
+            0 => {
+                return Err(AppError(format!(
+                    "old_str not found in {}{}. It must match the file exactly, including whitespace and indentation; read the file again and retry.",
+                    path, which
+                )));
+            }
+            1 => content = content.replacen(&edit.old_str, &edit.new_str, 1),
+            _ if edit.replace_all => content = content.replace(&edit.old_str, &edit.new_str),
+            n => {
+                let lines: Vec<String> = content
+                    .match_indices(&edit.old_str)
+                    .map(|(offset, _)| (content[..offset].matches('\n').count() + 1).to_string())
+                    .collect();
+                return Err(AppError(format!(
+                    "old_str matches {} times in {}{} (lines {}). Include more surrounding context to make it unique, or set replace_all.",
+                    n,
+                    path,
+                    which,
+                    lines.join(", ")
+                )));
+            }
+        }
+    }
+    Ok(content)
+}
+
+/// Writes through a temporary sibling file so readers never see a partial edit.
+/// The file keeps its permissions, e.g. the executable bit of a script.
+fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp = path.with_file_name(format!(".{}.pai-tmp", name));
+
+    fs::write(&temp, content)
+        .and_then(|_| match fs::metadata(path) {
+            Ok(meta) => fs::set_permissions(&temp, meta.permissions()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        })
+        .and_then(|_| fs::rename(&temp, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp);
+            AppError(format!("Failed to write '{}': {}", path.display(), e))
+        })
+}
+
+#[async_trait]
+impl Tool for StrReplaceTool {
+    fn name(&self) -> &str {
+        "str_replace"
+    }
+
+    fn description(&self) -> &str {
+        "Edit an existing file by replacing exact snippets. Give 'old_str' and 'new_str' for one edit, or an 'edits' array for several; old_str must match the file exactly and, unless replace_all is set, exactly once. Edits apply in order and either all succeed or the file is left unchanged."
+    }
+
+    fn schema(&self) -> ToolSchema {
+        let mut schema = object_schema(
+            &[
+                ("path", "string", "The relative path of the file to edit."),
+                (
+                    "old_str",
+                    "string",
+                    "Exact text to replace, including whitespace. Must be unique in the file unless replace_all is true.",
+                ),
+                ("new_str", "string", "Replacement text."),
+                (
+                    "replace_all",
+                    "boolean",
+                    "Replace every occurrence of old_str instead of requiring a unique match.",
+                ),
+                (
+                    "edits",
+                    "array",
+                    "Several edits applied in order, atomically. Use instead of old_str/new_str.",
+                ),
+            ],
+            &["path"],
+        );
+        if let Some(edits) = schema.properties.get_mut("edits") {
+            edits.items = Some(json!({
+                "type": "object",
+                "properties": {
+                    "old_str": {"type": "string"},
+                    "new_str": {"type": "string"},
+                    "replace_all": {"type": "boolean"}
+                },
+                "required": ["old_str", "new_str"]
+            }));
+        }
+        schema
+    }
+
+    fn is_mutating(&self) -> bool {
+        true
+    }
+
+    fn preview(&self, ctx: &ToolContext, input: &Value) -> String {
+        let path = optional_str(input, "path").unwrap_or("<missing path>");
+        let preview = || -> Result<String> {
+            let old = fs::read_to_string(ctx.resolve(path, Access::Write)?)
+                .map_err(|e| AppError(e.to_string()))?;
+            let new = apply_edits(path, &old, &Self::edits(input)?)?;
+            Ok(diff::colored(path, Some(&old), &new))
+        };
+        preview().unwrap_or_else(|e| format!("{} (the edit will fail: {})", path, e))
+    }
+
+    async fn call(&self, ctx: &ToolContext, input: Value) -> Result<String> {
+        let path_str = required_str(&input, "path", "str_replace")?;
+        let edits = Self::edits(&input)?;
+        let path = ctx.resolve(path_str, Access::Write)?;
+
+        let old = fs::read_to_string(&path)
+            .map_err(|e| AppError(format!("Failed to read file '{}': {}", path_str, e)))?;
+        let new = apply_edits(path_str, &old, &edits)?;
+        write_atomically(&path, &new)?;
+
+        Ok(format!(
+            "Applied {} edit{} to {} ({})",
+            edits.len(),
+            if edits.len() == 1 { "" } else { "s" },
+            path_str,
+            diff::summary(path_str, Some(&old), &new)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::Workspace;
+
+    #[tokio::test]
+    async fn test_str_replace_is_atomic() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.rs"), "let a = 1;\nlet b = 1;\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let executable = fs::Permissions::from_mode(0o755);
+            fs::set_permissions(dir.path().join("a.rs"), executable).unwrap();
+        }
+        let ctx = ToolContext::new(Workspace::new(dir.path()).unwrap());
+
+        let ambiguous = StrReplaceTool
+            .call(
+                &ctx,
+                json!({"path": "a.rs", "old_str": "= 1", "new_str": "= 2"}),
+            )
+            .await
+            .unwrap_err();
+        assert!(ambiguous.0.contains("matches 2 times"), "{}", ambiguous);
+
+        let missing = StrReplaceTool
+            .call(
+                &ctx,
+                json!({"path": "a.rs", "edits": [
+                    {"old_str": "let a", "new_str": "let x"},
+                    {"old_str": "let c", "new_str": "let y"}
+                ]}),
+            )
+            .await
+            .unwrap_err();
+        assert!(missing.0.contains("(edit 2 of 2)"), "{}", missing);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.rs")).unwrap(),
+            "let a = 1;\nlet b = 1;\n"
+        );
+
+        StrReplaceTool
+            .call(
+                &ctx,
+                json!({"path": "a.rs", "edits": [
+                    {"old_str": "= 1", "new_str": "= 2", "replace_all": true},
+                    {"old_str": "let a", "new_str": "let x"}
+                ]}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a.rs")).unwrap(),
+            "let x = 2;\nlet b = 2;\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.path().join("a.rs"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+    }
+}
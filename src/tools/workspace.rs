@@ -0,0 +1,279 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::Result;
+use crate::models::AppError;
+
+/// Secrets the agent may not read unless explicitly allowed.
+pub const DEFAULT_READ_DENY: &[&str] = &[
+    "**/.env",
+    "**/.env.local",
+    "**/*.pem",
+    "**/*.key",
+    "**/id_rsa",
+    "**/id_ed25519",
+];
+
+/// Paths the agent may never write by default.
+pub const DEFAULT_WRITE_DENY: &[&str] = &["**/.git/**"];
+
+/// `[code.sandbox]` section of `.pai.toml`. Patterns are globs relative to the root.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Workspace root; defaults to the current directory.
+    pub root: Option<PathBuf>,
+    /// When set, only matching paths can be read.
+    #[serde(default)]
+    pub read_allow: Vec<String>,
+    /// Paths that can never be read, in addition to the defaults.
+    #[serde(default)]
+    pub read_deny: Vec<String>,
+    /// When set, only matching paths can be written.
+    #[serde(default)]
+    pub write_allow: Vec<String>,
+    /// Paths that can never be written, in addition to the defaults.
+    #[serde(default)]
+    pub write_deny: Vec<String>,
+}
+
+/// Whether a tool reads or modifies a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Access::Read => write!(f, "Reading"),
+            Access::Write => write!(f, "Writing"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Patterns {
+    globs: Vec<String>,
+    set: GlobSet,
+}
+
+impl Patterns {
+    fn new<'a>(globs: impl IntoIterator<Item = &'a str>) -> Result<Self> {
+        let globs: Vec<String> = globs.into_iter().map(String::from).collect();
+        let mut builder = GlobSetBuilder::new();
+        for glob in &globs {
+            builder.add(
+                Glob::new(glob)
+                    .map_err(|e| AppError(format!("Invalid sandbox pattern '{}': {}", glob, e)))?,
+            );
+        }
+        let set = builder
+            .build()
+            .map_err(|e| AppError(format!("Invalid sandbox patterns: {}", e)))?;
+        Ok(Patterns { globs, set })
+    }
+
+    fn first_match(&self, path: &Path) -> Option<&str> {
+        self.set
+            .matches(path)
+            .first()
+            .map(|&index| self.globs[index].as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rules {
+    allow: Patterns,
+    deny: Patterns,
+}
+
+/// Confines tool paths to a root directory and applies read/write allow and deny lists.
+///
+/// Paths are canonicalized before checking, so `..` components and symlinks
+/// cannot reach outside the root.
+#[derive(Debug, Clone)]
+pub struct Workspace {
+    root: PathBuf,
+    read: Rules,
+    write: Rules,
+}
+
+impl Workspace {
+    /// Workspace over `root` with the default deny lists only.
+    pub fn new(root: impl AsRef<Path>) -> Result<Self> {
+        Self::from_config(root, &SandboxConfig::default())
+    }
+
+    /// Workspace over `root` (or `config.root`, resolved against `root`).
+    pub fn from_config(root: impl AsRef<Path>, config: &SandboxConfig) -> Result<Self> {
+        let root = match &config.root {
+            Some(configured) => root.as_ref().join(configured),
+            None => root.as_ref().to_path_buf(),
+        };
+        let root = root.canonicalize().map_err(|e| {
+            AppError(format!(
+                "Invalid workspace root '{}': {}",
+                root.display(),
+                e
+            ))
+        })?;
+
+        let strs = |list: &'static [&'static str], extra: &[String]| -> Vec<String> {
+            list.iter()
+                .map(|s| s.to_string())
+                .chain(extra.iter().cloned())
+                .collect()
+        };
+        let read_deny = strs(DEFAULT_READ_DENY, &config.read_deny);
+        let write_deny = strs(DEFAULT_WRITE_DENY, &config.write_deny);
+
+        Ok(Workspace {
+            root,
+            read: Rules {
+                allow: Patterns::new(config.read_allow.iter().map(String::as_str))?,
+                deny: Patterns::new(read_deny.iter().map(String::as_str))?,
+            },
+            write: Rules {
+                allow: Patterns::new(config.write_allow.iter().map(String::as_str))?,
+                deny: Patterns::new(write_deny.iter().map(String::as_str))?,
+            },
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `path` relative to the root; paths outside it are returned unchanged.
+    pub fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(&self.root).unwrap_or(path)
+    }
+
+    /// Resolves a tool path argument to a canonical path inside the root and
+    /// checks it against the rules for `access`.
+    pub fn resolve(&self, path: &str, access: Access) -> Result<PathBuf> {
+        let resolved = self.canonicalize(Path::new(path))?;
+        let relative = resolved.strip_prefix(&self.root).map_err(|_| {
+            AppError(format!(
+                "Path '{}' is outside the workspace root '{}'",
+                path,
+                self.root.display()
+            ))
+        })?;
+        self.check(relative, access)?;
+        Ok(resolved)
+    }
+
+    /// Checks a path relative to the root against the allow and deny lists.
+    pub fn check(&self, relative: &Path, access: Access) -> Result<()> {
+        let rules = match access {
+            Access::Read => &self.read,
+            Access::Write => &self.write,
+        };
+
+        if let Some(pattern) = rules.deny.first_match(relative) {
+            return Err(AppError(format!(
+                "{} '{}' is denied by the workspace rule '{}'",
+                access,
+                relative.display(),
+                pattern
+            )));
+        }
+        if !rules.allow.globs.is_empty() && rules.allow.first_match(relative).is_none() {
+            return Err(AppError(format!(
+                "{} '{}' is not allowed by the workspace rules",
+                access,
+                relative.display()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Canonicalizes `path` against the root. Paths that do not exist yet are
+    /// resolved through their deepest existing ancestor, so a new file inside a
+    /// symlinked directory still resolves to the symlink target. A `..` below a
+    /// missing directory cannot be resolved and is an error.
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf> {
+        let joined = self.root.join(path);
+        let mut existing = joined.as_path();
+        let mut missing = Vec::new();
+
+        while !existing.exists() {
+            let Some(name) = existing.file_name() else {
+                break;
+            };
+            missing.push(name.to_os_string());
+            existing = existing.parent().unwrap_or(Path::new("/"));
+        }
+
+        let mut resolved = existing.canonicalize().map_err(|e| {
+            AppError(format!(
+                "Failed to resolve path '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+        for name in missing.iter().rev() {
+            resolved.push(name);
+        }
+
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_resolve_rejects_escapes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(dir.path().join("secret.txt"), "s").unwrap();
+        let workspace = Workspace::new(&root).unwrap();
+
+        assert!(workspace.resolve("src/new.rs", Access::Write).is_ok());
+        assert!(workspace.resolve("../secret.txt", Access::Read).is_err());
+        assert!(
+            workspace
+                .resolve("src/../../secret.txt", Access::Read)
+                .is_err()
+        );
+        assert!(workspace.resolve("/etc/passwd", Access::Read).is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path(), root.join("escape")).unwrap();
+            assert!(
+                workspace
+                    .resolve("escape/secret.txt", Access::Read)
+                    .is_err()
+            );
+            assert!(workspace.resolve("escape/new.txt", Access::Write).is_err());
+        }
+    }
+
+    #[test]
+    fn test_allow_and_deny_lists() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SandboxConfig {
+            write_allow: vec!["src/**".to_string()],
+            read_deny: vec!["private/**".to_string()],
+            ..SandboxConfig::default()
+        };
+        let workspace = Workspace::from_config(dir.path(), &config).unwrap();
+
+        assert!(workspace.resolve("src/lib.rs", Access::Write).is_ok());
+        assert!(workspace.resolve("README.md", Access::Write).is_err());
+        assert!(workspace.resolve("README.md", Access::Read).is_ok());
+        assert!(workspace.resolve("private/notes.md", Access::Read).is_err());
+        assert!(workspace.resolve(".env", Access::Read).is_err());
+        assert!(workspace.resolve("src/.git/config", Access::Write).is_err());
+    }
+}